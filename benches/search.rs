@@ -0,0 +1,212 @@
+//! Throughput of the search loop and of the settings that tune it
+//!
+//! `cargo bench` runs every group, `cargo bench -- batch_size` only one of them.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hashsearch::{
+    adaptive_batch_size, spawn_workers, Algo, Check, Config, Direction, DEFAULT_BATCH_SIZE,
+    DEFAULT_CHANNEL_CAPACITY, UNBOUNDED_CHANNEL,
+};
+use sha2::{
+    digest::{
+        core_api::BlockSizeUser, FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser,
+        Reset, Update,
+    },
+    Digest, Sha256,
+};
+use std::{hint::black_box, thread};
+
+/// Candidates hashed in every iteration
+const CANDIDATES: usize = 1 << 16;
+
+/// Config hashing the first [`CANDIDATES`] integers with a single worker
+fn config(check: Check) -> Config {
+    Config {
+        end: Some(1 + CANDIDATES),
+        ..Config::new(check, 1)
+    }
+}
+
+/// Runs the search of `config` to the end of its range, returning the number of results
+fn scan(config: Config) -> usize {
+    let workers = Algo::Sha256.spawn_workers(config).unwrap();
+    workers.results().iter().count()
+}
+
+/// SHA-256 under a type of its own, which the search hashes one candidate at a time
+/// like any digest but SHA-256 itself
+#[derive(Clone, Default)]
+struct OneAtATime(Sha256);
+
+impl HashMarker for OneAtATime {}
+
+impl OutputSizeUser for OneAtATime {
+    type OutputSize = <Sha256 as OutputSizeUser>::OutputSize;
+}
+
+impl BlockSizeUser for OneAtATime {
+    type BlockSize = <Sha256 as BlockSizeUser>::BlockSize;
+}
+
+impl Update for OneAtATime {
+    fn update(&mut self, data: &[u8]) {
+        Update::update(&mut self.0, data)
+    }
+}
+
+impl FixedOutput for OneAtATime {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        FixedOutput::finalize_into(self.0, out)
+    }
+}
+
+impl Reset for OneAtATime {
+    fn reset(&mut self) {
+        Reset::reset(&mut self.0)
+    }
+}
+
+impl FixedOutputReset for OneAtATime {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        FixedOutputReset::finalize_into_reset(&mut self.0, out)
+    }
+}
+
+fn zeros(nibbles: usize) -> Check {
+    Check::zeros(nibbles, 32, Direction::Trailing)
+}
+
+fn hashing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hashing");
+    group.throughput(Throughput::Elements(CANDIDATES as u64));
+    // the cost of the digests one at a time, which the search loop beats with SIMD lanes
+    group.bench_function("bare sha256", |b| {
+        b.iter(|| {
+            for n in 1..=CANDIDATES {
+                black_box(Sha256::digest(n.to_le_bytes()));
+            }
+        })
+    });
+    group.bench_function("search", |b| b.iter(|| scan(config(Check::NEVER))));
+    // a whole block of prefix bytes is absorbed once instead of for every candidate
+    group.bench_function("64 prefix bytes", |b| {
+        b.iter(|| {
+            scan(Config {
+                prefix_bytes: vec![0xab; 64],
+                ..config(Check::NEVER)
+            })
+        })
+    });
+    // one candidate in 16 passes, so results are built and sent all the time
+    group.bench_function("many results", |b| b.iter(|| scan(config(zeros(1)))));
+    group.finish();
+}
+
+fn lanes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lanes");
+    group.throughput(Throughput::Elements(CANDIDATES as u64));
+    for double in [false, true] {
+        let config = || Config {
+            double,
+            ..config(Check::NEVER)
+        };
+        let rounds = if double { "double " } else { "" };
+        group.bench_function(format!("{rounds}one at a time"), |b| {
+            b.iter(|| {
+                let workers = spawn_workers::<OneAtATime>(config()).unwrap();
+                workers.results().iter().count()
+            })
+        });
+        group.bench_function(format!("{rounds}in lanes"), |b| b.iter(|| scan(config())));
+    }
+    group.finish();
+}
+
+fn batch_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_size");
+    group.throughput(Throughput::Elements(CANDIDATES as u64));
+    for nibbles in [1, 3, 5, 7] {
+        let check = zeros(nibbles);
+        let adaptive = adaptive_batch_size(&[check], 32);
+        for (name, batch_size) in [("fixed", DEFAULT_BATCH_SIZE), ("adaptive", adaptive)] {
+            group.bench_with_input(BenchmarkId::new(name, nibbles), &batch_size, |b, &size| {
+                b.iter(|| {
+                    scan(Config {
+                        batch_size: size,
+                        ..config(check)
+                    })
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn channel_capacity(c: &mut Criterion) {
+    let mut group = c.benchmark_group("channel_capacity");
+    group.throughput(Throughput::Elements(CANDIDATES as u64));
+    let capacities = [
+        ("1", 1),
+        ("default", DEFAULT_CHANNEL_CAPACITY),
+        ("unbounded", UNBOUNDED_CHANNEL),
+    ];
+    for (name, capacity) in capacities {
+        // every candidate passes, so the workers wait whenever the channel is full
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                scan(Config {
+                    channel_capacity: capacity,
+                    workers: 2,
+                    ..config(Check::EMPTY)
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+fn placement(c: &mut Criterion) {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut group = c.benchmark_group("placement");
+    group.throughput(Throughput::Elements(CANDIDATES as u64));
+    for pin in [false, true] {
+        let name = if pin { "pinned" } else { "unpinned" };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                scan(Config {
+                    workers,
+                    pin,
+                    ..config(Check::NEVER)
+                })
+            })
+        });
+    }
+    #[cfg(feature = "rayon")]
+    {
+        let pool = std::sync::Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(workers)
+                .build()
+                .unwrap(),
+        );
+        group.bench_function("rayon", |b| {
+            b.iter(|| {
+                let workers = Algo::Sha256
+                    .spawn_pool_workers(config(Check::NEVER), pool.clone())
+                    .unwrap();
+                workers.results().iter().count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    hashing,
+    lanes,
+    batch_size,
+    channel_capacity,
+    placement
+);
+criterion_main!(benches);
@@ -0,0 +1,282 @@
+#[cfg(feature = "rayon")]
+use crate::pool::spawn_pool_workers;
+use crate::{
+    best::spawn_best_workers,
+    histogram::zero_histogram,
+    lines::spawn_line_workers,
+    worker::{spawn_workers, verify, Rehash, Tester},
+    Check, Config, DigestBytes, Direction, LineBatch, SearchError, Workers,
+};
+#[cfg(feature = "blake3")]
+use blake3::Hasher as Blake3;
+use crossbeam_channel::Receiver;
+#[cfg(feature = "md5")]
+use md5::Md5;
+#[cfg(feature = "rayon")]
+use rayon::ThreadPool;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "sha1")]
+use sha1::Sha1;
+#[cfg(feature = "sha512")]
+use sha2::Sha512;
+use sha2::{
+    digest::{core_api::BlockSizeUser, FixedOutputReset},
+    Digest, Sha256,
+};
+#[cfg(feature = "sha3")]
+use sha3::{Keccak256, Sha3_256};
+#[cfg(feature = "rayon")]
+use std::sync::Arc;
+use std::{fmt, str::FromStr};
+
+/// Evaluates `$body` with `$D` standing for the hasher of `$algo`
+macro_rules! with_hasher {
+    ($algo:expr, $D:ident => $body:expr) => {
+        match $algo {
+            Algo::Sha256 => {
+                type $D = Sha256;
+                $body
+            }
+            #[cfg(feature = "sha512")]
+            Algo::Sha512 => {
+                type $D = Sha512;
+                $body
+            }
+            #[cfg(feature = "sha1")]
+            Algo::Sha1 => {
+                type $D = Sha1;
+                $body
+            }
+            #[cfg(feature = "md5")]
+            Algo::Md5 => {
+                type $D = Md5;
+                $body
+            }
+            #[cfg(feature = "blake3")]
+            Algo::Blake3 => {
+                type $D = Blake3;
+                $body
+            }
+            #[cfg(feature = "sha3")]
+            Algo::Sha3_256 => {
+                type $D = Sha3_256;
+                $body
+            }
+            #[cfg(feature = "sha3")]
+            Algo::Keccak256 => {
+                type $D = Keccak256;
+                $body
+            }
+        }
+    };
+}
+
+/// Supported hash algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Algo {
+    Sha256,
+    #[cfg(feature = "sha512")]
+    Sha512,
+    #[cfg(feature = "sha1")]
+    Sha1,
+    #[cfg(feature = "md5")]
+    Md5,
+    #[cfg(feature = "blake3")]
+    Blake3,
+    #[cfg(feature = "sha3")]
+    #[cfg_attr(feature = "serde", serde(rename = "sha3-256"))]
+    Sha3_256,
+    /// Keccak with its original padding, as used by Ethereum rather than SHA-3
+    #[cfg(feature = "sha3")]
+    #[cfg_attr(feature = "serde", serde(rename = "keccak-256"))]
+    Keccak256,
+}
+
+impl FromStr for Algo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, feature)) = Self::DISABLED.iter().find(|(name, _)| *name == s) {
+            return Err(format!(
+                "{s} is not part of this build, rebuild with `--features {feature}`"
+            ));
+        }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|algo| algo.to_string() == s)
+            .ok_or_else(|| {
+                let names = Self::ALL.iter().map(Self::to_string);
+                format!(
+                    "unknown algorithm `{s}`, expected one of: {}",
+                    names.collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for Algo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sha256 => "sha256",
+            #[cfg(feature = "sha512")]
+            Self::Sha512 => "sha512",
+            #[cfg(feature = "sha1")]
+            Self::Sha1 => "sha1",
+            #[cfg(feature = "md5")]
+            Self::Md5 => "md5",
+            #[cfg(feature = "blake3")]
+            Self::Blake3 => "blake3",
+            #[cfg(feature = "sha3")]
+            Self::Sha3_256 => "sha3-256",
+            #[cfg(feature = "sha3")]
+            Self::Keccak256 => "keccak-256",
+        })
+    }
+}
+
+impl Algo {
+    /// Every algorithm enabled in this build
+    pub const ALL: &'static [Self] = &[
+        Self::Sha256,
+        #[cfg(feature = "sha512")]
+        Self::Sha512,
+        #[cfg(feature = "sha1")]
+        Self::Sha1,
+        #[cfg(feature = "md5")]
+        Self::Md5,
+        #[cfg(feature = "blake3")]
+        Self::Blake3,
+        #[cfg(feature = "sha3")]
+        Self::Sha3_256,
+        #[cfg(feature = "sha3")]
+        Self::Keccak256,
+    ];
+
+    /// Algorithms left out of this build, along with the feature enabling them
+    const DISABLED: &'static [(&'static str, &'static str)] = &[
+        #[cfg(not(feature = "sha512"))]
+        ("sha512", "sha512"),
+        #[cfg(not(feature = "sha1"))]
+        ("sha1", "sha1"),
+        #[cfg(not(feature = "md5"))]
+        ("md5", "md5"),
+        #[cfg(not(feature = "blake3"))]
+        ("blake3", "blake3"),
+        #[cfg(not(feature = "sha3"))]
+        ("sha3-256", "sha3"),
+        #[cfg(not(feature = "sha3"))]
+        ("keccak-256", "sha3"),
+    ];
+
+    /// Size of the algorithm's digest, in bytes
+    pub fn digest_size(self) -> usize {
+        with_hasher!(self, D => <D as Digest>::output_size())
+    }
+
+    /// Runs [`spawn_workers`] with the algorithm's hasher
+    pub fn spawn_workers(self, config: Config) -> Result<Workers, SearchError> {
+        with_hasher!(self, D => spawn_workers::<D>(config))
+    }
+
+    /// Runs [`verify`] with the algorithm's hasher
+    pub fn verify(
+        self,
+        config: &Config,
+        n: usize,
+    ) -> Result<(DigestBytes, Option<usize>), SearchError> {
+        with_hasher!(self, D => verify::<D>(config, n))
+    }
+
+    /// Runs [`spawn_best_workers`] with the algorithm's hasher
+    pub fn spawn_best_workers(
+        self,
+        config: Config,
+        direction: Direction,
+    ) -> Result<Workers, SearchError> {
+        with_hasher!(self, D => spawn_best_workers::<D>(config, direction))
+    }
+
+    /// Runs [`zero_histogram`] with the algorithm's hasher
+    pub fn zero_histogram(
+        self,
+        config: &Config,
+        direction: Direction,
+    ) -> Result<Vec<u64>, SearchError> {
+        with_hasher!(self, D => zero_histogram::<D>(config, direction))
+    }
+
+    /// [`Tester`] with the algorithm's hasher, for [`Config::also`]
+    pub(crate) fn rehasher(
+        self,
+        checks: &[Check],
+        prefix_bytes: &[u8],
+        double: bool,
+        hmac_key: Option<&[u8]>,
+    ) -> Box<dyn Rehash> {
+        fn boxed<D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send + 'static>(
+            checks: &[Check],
+            prefix_bytes: &[u8],
+            double: bool,
+            hmac_key: Option<&[u8]>,
+        ) -> Box<dyn Rehash> {
+            Box::new(Tester::<D>::new(
+                checks,
+                prefix_bytes,
+                double,
+                hmac_key,
+                &[],
+            ))
+        }
+        with_hasher!(self, D => boxed::<D>(checks, prefix_bytes, double, hmac_key))
+    }
+
+    /// Runs [`spawn_line_workers`] with the algorithm's hasher
+    pub fn spawn_line_workers(
+        self,
+        config: Config,
+        lines: Receiver<LineBatch>,
+    ) -> Result<Workers, SearchError> {
+        with_hasher!(self, D => spawn_line_workers::<D>(config, lines))
+    }
+
+    /// Runs [`spawn_pool_workers`] with the algorithm's hasher
+    #[cfg(feature = "rayon")]
+    pub fn spawn_pool_workers(
+        self,
+        config: Config,
+        pool: Arc<ThreadPool>,
+    ) -> Result<Workers, SearchError> {
+        with_hasher!(self, D => spawn_pool_workers::<D>(config, pool))
+    }
+}
+
+#[cfg(all(test, feature = "sha3"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha3_and_keccak_of_nothing() {
+        let cases = [
+            (
+                "sha3-256",
+                "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+            ),
+            (
+                "keccak-256",
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            ),
+        ];
+        for (name, digest) in cases {
+            let mut rehasher = name
+                .parse::<Algo>()
+                .unwrap()
+                .rehasher(&[], &[], false, None);
+            rehasher.hash(b"");
+            assert_eq!(rehasher.digest().to_string(), digest, "{name}");
+        }
+    }
+}
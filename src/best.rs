@@ -0,0 +1,118 @@
+use crate::{
+    encoding::MAX_ENCODED_SIZE,
+    worker::{core_for, pin_to, results_channel, Candidates, Field, Tester},
+    CancelToken, Config, Direction, Progress, SearchError, Workers,
+};
+use log::{debug, info};
+use sha2::{
+    digest::{core_api::BlockSizeUser, FixedOutputReset},
+    Digest,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// Like [`crate::spawn_workers`], but sends every candidate whose digest starts or ends
+/// with more zero nibbles than any before it instead of those passing a check
+///
+/// Workers share the best count so far, so every record is sent once,
+/// though records found at nearly the same time can arrive out of order,
+/// a record with fewer zeros than one received before it having been beaten already.
+/// [`DigestBytes::zero_nibbles`](crate::DigestBytes::zero_nibbles) gives the count of a result.
+/// The checks of `config` do not apply.
+/// Fails if `config` does not [validate](Config::validate),
+/// or has a seed, further algorithms or `resume`.
+pub fn spawn_best_workers<D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send + 'static>(
+    config: Config,
+    direction: Direction,
+) -> Result<Workers, SearchError> {
+    config.validate_for::<D>(&[Field::Seed, Field::Also, Field::Resume])?;
+    let Config {
+        workers,
+        start,
+        end,
+        step,
+        descending,
+        encoding,
+        width,
+        prefix_bytes,
+        double,
+        hmac_key,
+        channel_capacity,
+        overflow,
+        pin,
+        numa,
+        cancel,
+        batch_size,
+        ..
+    } = config;
+
+    let stop = CancelToken::flag(cancel);
+    let candidates = Candidates::new(start, end, step, descending);
+    info!(
+        "spawning {workers} workers looking for the best hash in {} candidates from {start}",
+        candidates.len
+    );
+    let progress = Arc::new(Progress::new(
+        (0..workers)
+            .map(|i| AtomicUsize::new(candidates.frontier(batch_size.saturating_mul(i))))
+            .collect(),
+        workers,
+    ));
+    let (result_tx, result_rx) = results_channel(channel_capacity, overflow, &stop, &progress);
+    let best = Arc::new(AtomicUsize::new(0));
+    let setup = Arc::new((prefix_bytes, hmac_key));
+
+    let handles = (0..workers).map(|i| {
+        let th_result_tx = result_tx.clone();
+        let th_stop = stop.clone();
+        let th_progress = progress.clone();
+        let th_best = best.clone();
+        let th_setup = setup.clone();
+        let core = core_for(pin, numa, i);
+
+        thread::spawn(move || {
+            pin_to(core);
+            let (prefix_bytes, hmac_key) = &*th_setup;
+            let mut tester = Tester::<D>::new(&[], prefix_bytes, double, hmac_key.as_deref(), &[]);
+            let mut results_buf = Vec::new();
+            let mut input = [0; MAX_ENCODED_SIZE];
+            let first_batch = batch_size.saturating_mul(i);
+            let stopped_at =
+                candidates.scan(first_batch, batch_size, workers, &th_stop, |batch, next| {
+                    let hashed = batch.len();
+                    for position in batch {
+                        let n = candidates.at(position);
+                        let candidate = encoding.encode(n, width, &mut input);
+                        let zeros = tester.zero_nibbles(candidate, direction);
+                        // only the worker raising the shared maximum reports the record
+                        if zeros > th_best.load(Ordering::Relaxed)
+                            && th_best.fetch_max(zeros, Ordering::Relaxed) < zeros
+                        {
+                            results_buf.push(tester.found(n, position + 1, 0, candidate));
+                        }
+                    }
+
+                    if !th_result_tx.finish_batch(i, hashed, &mut results_buf) {
+                        return false;
+                    }
+                    th_progress.frontiers[i].store(candidates.frontier(next), Ordering::Release);
+                    true
+                });
+            if stopped_at >= candidates.len {
+                debug!("worker {i} finished its part of the range");
+                th_progress.finish();
+            }
+        })
+    });
+    Ok(Workers {
+        handles: handles.collect(),
+        results: result_rx,
+        stop,
+        progress,
+    })
+}
@@ -0,0 +1,75 @@
+use crate::{worker::STOP_POLL_INTERVAL, Algo, Config, Match, SearchError};
+use crossbeam_channel::RecvTimeoutError;
+use log::info;
+use std::thread;
+use tokio::sync::{
+    mpsc::{self, Receiver},
+    Semaphore,
+};
+
+/// Results of [`search_async`], like those of [`crate::SearchResults`]
+pub type AsyncResults = Receiver<Result<Match, SearchError>>;
+
+/// Runs a search with `algo` like [`crate::search_with`],
+/// forwarding results to a tokio channel so they can be awaited
+///
+/// Workers run on their own threads, as does the bridge forwarding their results,
+/// so no runtime thread ever blocks on the search.
+/// The channel holds [`Config::channel_capacity`] results, which must not be 0,
+/// up to the most a tokio channel can if it is [`crate::UNBOUNDED_CHANNEL`].
+/// Dropping the receiver stops the workers, as does [`Config::cancel`].
+/// The channel is closed once the search ends,
+/// after receiving [`SearchError::AllWorkersDead`] if the workers exited early.
+/// Fails if `config` is invalid.
+pub fn search_async(algo: Algo, config: Config) -> Result<AsyncResults, SearchError> {
+    config.validate()?;
+    if config.channel_capacity == 0 {
+        return Err(SearchError::InvalidConfig(
+            "tokio channels need room for at least 1 result",
+        ));
+    }
+    // tokio allocates its channels as they fill, so an unbounded one is merely very large
+    let (tx, rx) = mpsc::channel(config.channel_capacity.min(Semaphore::MAX_PERMITS));
+    let workers = algo.spawn_workers(config)?;
+    // dropping the workers at the end of the thread stops them
+    thread::spawn(move || loop {
+        let found = match workers.results().recv_timeout(STOP_POLL_INTERVAL) {
+            Ok(found) => found,
+            // a search finding nothing has to notice the receiver being dropped meanwhile
+            Err(RecvTimeoutError::Timeout) if tx.is_closed() => {
+                info!("search stopped, the async receiver was dropped");
+                return;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                if !workers.progress().exhausted() && !workers.stopped() {
+                    let _ = tx.blocking_send(Err(SearchError::AllWorkersDead));
+                }
+                return;
+            }
+        };
+        if tx.blocking_send(Ok(Match::new(&found, algo))).is_err() {
+            info!("search stopped, the async receiver was dropped");
+            return;
+        }
+    });
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Check;
+
+    #[test]
+    fn zero_capacity_is_rejected() {
+        let config = Config {
+            channel_capacity: 0,
+            ..Config::new(Check::EMPTY, 1)
+        };
+        assert!(matches!(
+            search_async(Algo::Sha256, config),
+            Err(SearchError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::SearchError;
+use std::{array, cmp::Ordering, fmt, ops::Range, str::FromStr};
+
+/// Size of the largest supported digest, in bytes
+pub const MAX_DIGEST_SIZE: usize = 64;
+
+/// Digest reinterpreted as machine words for masking
+pub(crate) type Words = [u64; MAX_DIGEST_SIZE / 8];
+
+/// End of the digest's hex representation that is checked
+#[derive(Clone, Copy)]
+pub enum Direction {
+    Leading,
+    Trailing,
+}
+
+/// Hex digits a digest must contain, one nibble per element
+#[derive(Clone)]
+pub struct HexPattern(pub Vec<u8>);
+
+impl FromStr for HexPattern {
+    type Err = SearchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(SearchError::InvalidPattern(
+                "hex pattern must not be empty".into(),
+            ));
+        }
+        s.chars()
+            .map(|c| match c.to_digit(16) {
+                Some(d) => Ok(d as u8),
+                None => Err(SearchError::InvalidPattern(format!(
+                    "invalid hex digit `{c}` in pattern `{s}`"
+                ))),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for HexPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|d| write!(f, "{d:x}"))
+    }
+}
+
+/// Bits of a digest that are checked and the values they must have
+#[derive(Clone, Copy)]
+pub struct Check {
+    pub(crate) mask: Words,
+    pub(crate) expected: Words,
+    /// largest digest accepted, as a big-endian integer
+    pub(crate) max: Option<Words>,
+}
+
+impl Check {
+    /// Check that accepts every digest
+    pub const EMPTY: Self = Self {
+        mask: [0; MAX_DIGEST_SIZE / 8],
+        expected: [0; MAX_DIGEST_SIZE / 8],
+        max: None,
+    };
+
+    /// Check that rejects every digest, for measuring throughput
+    pub const NEVER: Self = Self {
+        mask: [0; MAX_DIGEST_SIZE / 8],
+        // no masked digest can have bits outside the mask set
+        expected: [u64::MAX; MAX_DIGEST_SIZE / 8],
+        max: None,
+    };
+
+    /// Requires `nibbles` zero nibbles at the start or end of a `digest_size` byte digest
+    pub fn zeros(nibbles: usize, digest_size: usize, direction: Direction) -> Self {
+        Self {
+            mask: make_check_mask(nibbles, digest_size, direction),
+            ..Self::EMPTY
+        }
+    }
+
+    /// Requires `bits` zero bits at the start or end of a `digest_size` byte digest
+    pub fn zero_bits(bits: usize, digest_size: usize, direction: Direction) -> Self {
+        Self {
+            mask: make_bit_mask(bits, digest_size, direction),
+            ..Self::EMPTY
+        }
+    }
+
+    /// Requires the masked bits to repeat the hex digit `nibble` instead of being zero,
+    /// so `Check::zeros(5, ..).repeating(0xf)` looks for `fffff`
+    pub fn repeating(self, nibble: u8) -> Self {
+        let fill = u64::from_ne_bytes([(nibble & 0xf) * 0x11; 8]);
+        Self {
+            expected: self.mask.map(|m| m & fill),
+            ..self
+        }
+    }
+
+    /// Requires `pattern` at the start or end of a `digest_size` byte digest
+    pub fn pattern(pattern: &HexPattern, digest_size: usize, direction: Direction) -> Self {
+        Self {
+            mask: make_check_mask(pattern.0.len(), digest_size, direction),
+            expected: make_expected(&pattern.0, digest_size, direction),
+            max: None,
+        }
+    }
+
+    /// Requires the digest to equal `digest`
+    ///
+    /// Words are compared in order, so mismatches are rejected at the first word.
+    pub fn exact(digest: &[u8]) -> Self {
+        let mut mask = [0; MAX_DIGEST_SIZE];
+        let mut expected = [0; MAX_DIGEST_SIZE];
+        let len = digest.len().min(MAX_DIGEST_SIZE);
+        mask[..len].fill(0xff);
+        expected[..len].copy_from_slice(&digest[..len]);
+        Self {
+            mask: to_words(mask),
+            expected: to_words(expected),
+            max: None,
+        }
+    }
+
+    /// Requires the digest, read as a big-endian integer, to be at most `max`,
+    /// the way mining targets work
+    ///
+    /// `max` should be as long as the digest.
+    pub fn at_most(max: &[u8]) -> Self {
+        let mut bytes = [0; MAX_DIGEST_SIZE];
+        let len = max.len().min(MAX_DIGEST_SIZE);
+        bytes[..len].copy_from_slice(&max[..len]);
+        Self {
+            max: Some(to_words(bytes)),
+            ..Self::EMPTY
+        }
+    }
+
+    /// Adds the requirements of a check over disjoint bits,
+    /// keeping the lower maximum if both have one
+    pub fn merge(&mut self, other: &Self) {
+        for i in 0..self.mask.len() {
+            self.mask[i] |= other.mask[i];
+            self.expected[i] |= other.expected[i];
+        }
+        self.max = match (self.max, other.max) {
+            (Some(max), Some(other)) if compare_be(&other, &max) == Ordering::Less => Some(other),
+            (max, other) => max.or(other),
+        };
+    }
+
+    /// Bits of the digest that are checked, in digest byte order
+    pub fn mask(&self) -> [u8; MAX_DIGEST_SIZE] {
+        from_words(self.mask)
+    }
+
+    /// Values the checked bits must have, in digest byte order
+    pub fn expected(&self) -> [u8; MAX_DIGEST_SIZE] {
+        from_words(self.expected)
+    }
+
+    /// Largest digest accepted as a big-endian integer, if there is a limit
+    pub fn max(&self) -> Option<[u8; MAX_DIGEST_SIZE]> {
+        self.max.map(from_words)
+    }
+
+    /// Chance that a uniformly random `digest_size` byte digest passes
+    pub fn probability(&self, digest_size: usize) -> f64 {
+        let bytes = digest_size.min(MAX_DIGEST_SIZE);
+        if (0..bytes.div_ceil(8)).any(|i| self.expected[i] & !self.mask[i] != 0) {
+            return 0.0;
+        }
+        let masked: u32 = self.mask.iter().map(|w| w.count_ones()).sum();
+        let below_max = self.max.map_or(1.0, |max| {
+            // digests up to and including `max`, out of all digests
+            let max = from_words(max);
+            let fraction: f64 = max[..bytes]
+                .iter()
+                .rev()
+                .fold(0.0, |rest, &b| (b as f64 + rest) / 256.0);
+            fraction + 256f64.powi(-(bytes as i32))
+        });
+        0.5f64.powi(masked as i32) * below_max
+    }
+
+    /// Whether the first `words` words of a digest meet the requirements
+    #[inline]
+    pub(crate) fn passes(&self, hash: &Words, words: usize) -> bool {
+        let masked = hash[..words]
+            .iter()
+            .zip(&self.mask[..words])
+            .zip(&self.expected[..words])
+            .all(|((hb, mb), eb)| hb & mb == *eb);
+        masked
+            && self
+                .max
+                .is_none_or(|max| compare_be(&hash[..words], &max[..words]) != Ordering::Greater)
+    }
+
+    /// Mask followed by expected values of a 32 byte digest as big-endian `u32` words,
+    /// the way the OpenCL kernel compares SHA-256 state words
+    #[cfg(feature = "opencl")]
+    pub(crate) fn sha256_words(&self) -> [u32; 16] {
+        let (mask, expected) = (from_words(self.mask), from_words(self.expected));
+        let mut words = [0; 16];
+        let bytes = mask[..32]
+            .chunks_exact(4)
+            .chain(expected[..32].chunks_exact(4));
+        for (word, bytes) in words.iter_mut().zip(bytes) {
+            *word = u32::from_be_bytes(bytes.try_into().expect("chunks are 4 bytes"));
+        }
+        words
+    }
+}
+
+/// Compares digests as big-endian integers, stopping at the first differing word
+fn compare_be(a: &[u64], b: &[u64]) -> Ordering {
+    // words hold digest bytes in memory order, so they are read back as big-endian
+    a.iter()
+        .map(|w| u64::from_be(*w))
+        .cmp(b.iter().map(|w| u64::from_be(*w)))
+}
+
+/// Produces an array where all bits
+/// except for first or last `nibbles` nibbles of a `digest_size` byte digest are set to 0
+fn make_check_mask(nibbles: usize, digest_size: usize, direction: Direction) -> Words {
+    let mut bytes = [0; MAX_DIGEST_SIZE];
+    for position in nibble_positions(nibbles, digest_size, direction) {
+        set_nibble(&mut bytes, position, 0xf);
+    }
+    to_words(bytes)
+}
+
+/// Produces an array where all bits
+/// except for first or last `bits` bits of a `digest_size` byte digest are set to 0
+fn make_bit_mask(bits: usize, digest_size: usize, direction: Direction) -> Words {
+    let mut bytes = [0; MAX_DIGEST_SIZE];
+    for bit in 0..bits.min(digest_size * 8) {
+        match direction {
+            Direction::Leading => bytes[bit / 8] |= 0x80 >> (bit % 8),
+            Direction::Trailing => bytes[digest_size - 1 - bit / 8] |= 1 << (bit % 8),
+        }
+    }
+    to_words(bytes)
+}
+
+/// Produces an array holding `pattern` at the first or last nibbles
+/// of a `digest_size` byte digest, to be compared with a masked digest
+fn make_expected(pattern: &[u8], digest_size: usize, direction: Direction) -> Words {
+    let mut bytes = [0; MAX_DIGEST_SIZE];
+    let positions = nibble_positions(pattern.len(), digest_size, direction);
+    for (position, &digit) in positions.zip(pattern) {
+        set_nibble(&mut bytes, position, digit);
+    }
+    to_words(bytes)
+}
+
+/// Positions in the hex representation of a `digest_size` byte digest
+/// taken by `nibbles` nibbles at its start or end
+fn nibble_positions(nibbles: usize, digest_size: usize, direction: Direction) -> Range<usize> {
+    let total = digest_size * 2;
+    match direction {
+        Direction::Leading => 0..nibbles.min(total),
+        Direction::Trailing => total.saturating_sub(nibbles)..total,
+    }
+}
+
+/// Sets the nibble at hex `position` of a digest, even positions being high nibbles
+fn set_nibble(bytes: &mut [u8], position: usize, digit: u8) {
+    let shift = if position.is_multiple_of(2) { 4 } else { 0 };
+    bytes[position / 2] |= digit << shift;
+}
+
+/// Digest bytes as words in memory order, compiling down to a plain copy
+#[inline]
+pub(crate) fn to_words(bytes: [u8; MAX_DIGEST_SIZE]) -> Words {
+    array::from_fn(|i| {
+        let word = bytes[i * 8..i * 8 + 8].try_into();
+        u64::from_ne_bytes(word.expect("words are 8 bytes"))
+    })
+}
+
+fn from_words(words: Words) -> [u8; MAX_DIGEST_SIZE] {
+    array::from_fn(|i| words[i / 8].to_ne_bytes()[i % 8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passes(check: &Check, digest: [u8; 32]) -> bool {
+        let mut bytes = [0; MAX_DIGEST_SIZE];
+        bytes[..32].copy_from_slice(&digest);
+        check.passes(&to_words(bytes), 4)
+    }
+
+    #[test]
+    fn zero_bits_off_nibble_boundaries() {
+        let trailing = |bits| Check::zero_bits(bits, 32, Direction::Trailing).mask();
+        assert_eq!(trailing(1)[28..32], [0, 0, 0, 0x01]);
+        assert_eq!(trailing(7)[28..32], [0, 0, 0, 0x7f]);
+        assert_eq!(trailing(33)[26..32], [0, 0x01, 0xff, 0xff, 0xff, 0xff]);
+        let leading = Check::zero_bits(33, 32, Direction::Leading).mask();
+        assert_eq!(leading[..6], [0xff, 0xff, 0xff, 0xff, 0x80, 0]);
+
+        let check = Check::zero_bits(7, 32, Direction::Trailing);
+        let mut digest = [0xff; 32];
+        digest[31] = 0x80;
+        assert!(passes(&check, digest));
+        digest[31] = 0x81;
+        assert!(!passes(&check, digest));
+    }
+
+    #[test]
+    fn prefix_with_odd_trailing_zeros() {
+        let mut check = Check::pattern(&"abc".parse().unwrap(), 32, Direction::Leading);
+        check.merge(&Check::zeros(3, 32, Direction::Trailing));
+        assert_eq!(check.mask()[..2], [0xff, 0xf0]);
+        assert_eq!(check.mask()[30..32], [0x0f, 0xff]);
+        assert_eq!(check.expected()[..2], [0xab, 0xc0]);
+
+        let mut digest = [0x55; 32];
+        digest[..2].copy_from_slice(&[0xab, 0xc5]);
+        digest[30..].copy_from_slice(&[0x50, 0x00]);
+        assert!(passes(&check, digest));
+        // the nibbles next to both odd boundaries are free, the ones inside are not
+        digest[30] = 0x51;
+        assert!(!passes(&check, digest));
+        digest[30] = 0x50;
+        digest[1] = 0xb5;
+        assert!(!passes(&check, digest));
+    }
+}
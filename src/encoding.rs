@@ -0,0 +1,183 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// Size of the buffer candidates are serialized into, in bytes
+pub const MAX_ENCODED_SIZE: usize = 32;
+
+/// Widest binary encoding of a candidate, in bytes
+pub const MAX_WIDTH: usize = size_of::<u128>();
+
+/// How a candidate integer is serialized before hashing
+#[derive(Clone, Copy)]
+pub enum Encoding {
+    /// little-endian bytes, as many as the configured width
+    Le,
+    /// big-endian bytes, as many as the configured width
+    Be,
+    /// ASCII decimal digits, e.g. `12345`
+    Decimal,
+    /// ASCII decimal digits left-padded with `0` to the configured width, e.g. `00012345`,
+    /// [`crate::Config::validate`] rejects ranges with candidates that take more digits
+    PaddedDecimal,
+    /// ASCII lowercase hex digits without leading zeroes, e.g. `3039`
+    Hex,
+}
+
+impl Encoding {
+    /// Serializes `n` into `buf`, returning the bytes to be hashed
+    ///
+    /// Binary encodings take `width` bytes, at most [`MAX_WIDTH`],
+    /// and drop the high bytes of candidates that do not fit.
+    /// [`Self::PaddedDecimal`] takes at least `width` digits, at most [`MAX_ENCODED_SIZE`].
+    pub fn encode(self, n: usize, width: usize, buf: &mut [u8; MAX_ENCODED_SIZE]) -> &[u8] {
+        match self {
+            Self::Le => {
+                buf[..width].copy_from_slice(&(n as u128).to_le_bytes()[..width]);
+                &buf[..width]
+            }
+            Self::Be => {
+                buf[..width].copy_from_slice(&(n as u128).to_be_bytes()[MAX_WIDTH - width..]);
+                &buf[..width]
+            }
+            Self::Decimal => write_digits(n, 10, buf),
+            Self::PaddedDecimal => {
+                let digits = write_digits(n, 10, buf).len();
+                let start = MAX_ENCODED_SIZE - width.clamp(digits, MAX_ENCODED_SIZE);
+                buf[start..MAX_ENCODED_SIZE - digits].fill(b'0');
+                &buf[start..]
+            }
+            Self::Hex => write_digits(n, 16, buf),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "le" => Ok(Self::Le),
+            "be" => Ok(Self::Be),
+            "decimal" => Ok(Self::Decimal),
+            "hex" => Ok(Self::Hex),
+            _ => Err(format!(
+                "unknown encoding `{s}`, expected one of: le, be, decimal, hex"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Le => "le",
+            Self::Be => "be",
+            Self::Decimal => "decimal",
+            Self::PaddedDecimal => "padded decimal",
+            Self::Hex => "hex",
+        })
+    }
+}
+
+/// Raw bytes given as a hex string, e.g. `deadbeef`,
+/// displayed and serialized as lowercase hex
+#[derive(Clone, Default)]
+pub struct HexBytes(pub Vec<u8>);
+
+impl FromStr for HexBytes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.len().is_multiple_of(2) {
+            return Err(format!("hex bytes `{s}` have an odd number of digits"));
+        }
+        (0..s.len())
+            .step_by(2)
+            .map(|i| {
+                s.get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                    .ok_or_else(|| format!("invalid hex bytes `{s}`"))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for HexBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for HexBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Writes ASCII digits of `n` in `radix` to the end of `buf`
+fn write_digits(mut n: usize, radix: usize, buf: &mut [u8; MAX_ENCODED_SIZE]) -> &[u8] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = DIGITS[n % radix];
+        n /= radix;
+        if n == 0 {
+            break;
+        }
+    }
+    &buf[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(encoding: Encoding, n: usize, width: usize) -> Vec<u8> {
+        encoding
+            .encode(n, width, &mut [0; MAX_ENCODED_SIZE])
+            .to_vec()
+    }
+
+    #[test]
+    fn encodings() {
+        assert_eq!(
+            encode(Encoding::Le, 12345, 8),
+            [0x39, 0x30, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            encode(Encoding::Be, 12345, 8),
+            [0, 0, 0, 0, 0, 0, 0x30, 0x39]
+        );
+        assert_eq!(encode(Encoding::Be, 12345, 3), [0, 0x30, 0x39]);
+        assert_eq!(encode(Encoding::Le, 0x123456, 2), [0x56, 0x34]);
+        assert_eq!(encode(Encoding::Le, usize::MAX, 16)[8..], [0; 8]);
+        assert_eq!(encode(Encoding::Decimal, 12345, 8), b"12345");
+        assert_eq!(encode(Encoding::Decimal, 0, 8), b"0");
+        assert_eq!(encode(Encoding::Hex, 12345, 8), b"3039");
+        assert_eq!(encode(Encoding::Hex, usize::MAX, 8), b"ffffffffffffffff");
+        assert_eq!(encode(Encoding::PaddedDecimal, 12345, 8), b"00012345");
+        assert_eq!(encode(Encoding::PaddedDecimal, 12345, 5), b"12345");
+    }
+
+    #[test]
+    fn padded_decimal_candidates_must_fit() {
+        let config = |start, end| crate::Config {
+            encoding: Encoding::PaddedDecimal,
+            width: 3,
+            start,
+            end,
+            ..crate::Config::new(crate::Check::EMPTY, 1)
+        };
+        assert!(config(0, Some(1000)).validate().is_ok());
+        for (start, end) in [(12345, Some(12346)), (0, Some(1001)), (0, None)] {
+            assert!(matches!(
+                config(start, end).validate(),
+                Err(crate::SearchError::InvalidConfig(_))
+            ));
+        }
+    }
+}
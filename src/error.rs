@@ -0,0 +1,66 @@
+use std::{error::Error, fmt, io};
+
+/// Reasons a search cannot start or ended before it should have
+#[derive(Debug)]
+pub enum SearchError {
+    /// No zeros or more zeros were asked for than the digest has nibbles
+    InvalidNumZeros { num_zeros: usize, max: usize },
+    /// A hex pattern that does not parse, along with why
+    InvalidPattern(String),
+    /// [`crate::Config`] fields that are out of range or contradict each other
+    InvalidConfig(&'static str),
+    /// Workers disconnected the results channel without exhausting the range
+    /// or being stopped, which happens if they panic
+    AllWorkersDead,
+    /// Reading candidates or writing results failed
+    Io(io::Error),
+    /// The OpenCL device could not be set up or failed to run the kernel
+    #[cfg(feature = "opencl")]
+    Gpu(ocl::Error),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNumZeros { num_zeros: 0, .. } => {
+                f.write_str("zero count must be at least 1, otherwise every number matches")
+            }
+            Self::InvalidNumZeros { num_zeros, max } => {
+                write!(
+                    f,
+                    "{num_zeros} zeros exceed the {max} nibbles of the digest"
+                )
+            }
+            Self::InvalidPattern(reason) => f.write_str(reason),
+            Self::InvalidConfig(reason) => write!(f, "invalid search config: {reason}"),
+            Self::AllWorkersDead => f.write_str("all worker threads died before finishing"),
+            Self::Io(e) => e.fmt(f),
+            #[cfg(feature = "opencl")]
+            Self::Gpu(e) => write!(f, "OpenCL failed: {e}"),
+        }
+    }
+}
+
+impl Error for SearchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            #[cfg(feature = "opencl")]
+            Self::Gpu(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SearchError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl From<ocl::Error> for SearchError {
+    fn from(e: ocl::Error) -> Self {
+        Self::Gpu(e)
+    }
+}
@@ -0,0 +1,85 @@
+// SHA-256 of consecutive counters, recording the offsets of those whose digest passes a check
+//
+// Each work item hashes `base + id` serialized as `width` little or big-endian bytes,
+// which always fits a single block. A check is 8 mask words followed by 8 expected words,
+// both big-endian like the digest words.
+
+__constant uint K[64] = {
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+};
+
+__constant uint H0[8] = {
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+};
+
+#define ROTR(x, n) (((x) >> (n)) | ((x) << (32 - (n))))
+
+__kernel void search(
+    ulong base,
+    uint len,
+    uint width,
+    uint big_endian,
+    uint num_checks,
+    __constant uint *checks,
+    __global uint *count,
+    __global uint *hits
+) {
+    uint id = get_global_id(0);
+    if (id >= len) {
+        return;
+    }
+    ulong n = base + id;
+
+    uint w[64];
+    for (uint i = 0; i < 16; i++) {
+        w[i] = 0;
+    }
+    for (uint i = 0; i < width; i++) {
+        uint shift = big_endian ? (width - 1 - i) * 8 : i * 8;
+        w[i / 4] |= (uint)((n >> shift) & 0xff) << (24 - 8 * (i % 4));
+    }
+    w[width / 4] |= 0x80u << (24 - 8 * (width % 4));
+    w[15] = width * 8;
+    for (uint i = 16; i < 64; i++) {
+        uint s0 = ROTR(w[i - 15], 7) ^ ROTR(w[i - 15], 18) ^ (w[i - 15] >> 3);
+        uint s1 = ROTR(w[i - 2], 17) ^ ROTR(w[i - 2], 19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+    }
+
+    uint a = H0[0], b = H0[1], c = H0[2], d = H0[3];
+    uint e = H0[4], f = H0[5], g = H0[6], h = H0[7];
+    for (uint i = 0; i < 64; i++) {
+        uint t1 = h + (ROTR(e, 6) ^ ROTR(e, 11) ^ ROTR(e, 25)) + ((e & f) ^ (~e & g)) + K[i] + w[i];
+        uint t2 = (ROTR(a, 2) ^ ROTR(a, 13) ^ ROTR(a, 22)) + ((a & b) ^ (a & c) ^ (b & c));
+        h = g;
+        g = f;
+        f = e;
+        e = d + t1;
+        d = c;
+        c = b;
+        b = a;
+        a = t1 + t2;
+    }
+    uint digest[8] = {
+        H0[0] + a, H0[1] + b, H0[2] + c, H0[3] + d, H0[4] + e, H0[5] + f, H0[6] + g, H0[7] + h,
+    };
+
+    for (uint check = 0; check < num_checks; check++) {
+        __constant uint *mask = checks + check * 16;
+        uint passes = 1;
+        for (uint i = 0; i < 8; i++) {
+            passes &= (digest[i] & mask[i]) == mask[8 + i];
+        }
+        if (passes) {
+            hits[atomic_inc(count)] = id;
+            return;
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    encoding::MAX_ENCODED_SIZE,
+    worker::{results_channel, Field, Tester},
+    CancelToken, Check, Config, Encoding, Progress, SearchError, Workers,
+};
+use crossbeam_channel::bounded;
+use log::{debug, error, info, warn};
+use ocl::{flags::MemFlags, Buffer, Kernel, ProQue};
+use sha2::Sha256;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// Number of candidates hashed by one run of the kernel
+pub const GPU_BATCH_SIZE: usize = 1 << 22;
+
+/// SHA-256 kernel hashing consecutive candidates and recording those that pass a check
+const KERNEL: &str = include_str!("gpu.cl");
+
+/// Like [`crate::spawn_workers`] with SHA-256, but hashes on the first device
+/// of the default OpenCL platform
+///
+/// A thread feeds the device [`GPU_BATCH_SIZE`] candidates at a time
+/// and hashes the ones it reports again on the CPU before sending them,
+/// so results are the same as those of the CPU workers and in ascending order.
+/// [`Progress::worker_stats`] has a single entry for the device.
+/// Only little and big-endian candidates up to 8 bytes wide are supported,
+/// without prefix bytes, a second round, HMAC, further algorithms, a maximum digest, a seed,
+/// a step, descending candidates or `resume`.
+/// `config.workers`, `config.pin` and `config.batch_size` are ignored.
+/// Fails if `config` is not supported or the device cannot be set up,
+/// a device failing later ends the search as if its workers died.
+pub fn spawn_gpu_workers(config: Config) -> Result<Workers, SearchError> {
+    config.validate()?;
+    config.reject(&[Field::Sequence, Field::Resume])?;
+    let Config {
+        checks,
+        start,
+        end,
+        encoding,
+        width,
+        prefix_bytes,
+        double,
+        hmac_key,
+        also,
+        channel_capacity,
+        overflow,
+        seed,
+        cancel,
+        ..
+    } = config;
+    let big_endian = match encoding {
+        Encoding::Le => false,
+        Encoding::Be => true,
+        _ => {
+            return Err(SearchError::InvalidConfig(
+                "the GPU only hashes le and be candidates",
+            ))
+        }
+    };
+    if width > size_of::<u64>() {
+        return Err(SearchError::InvalidConfig(
+            "the GPU only hashes candidates up to 8 bytes wide",
+        ));
+    }
+    if !prefix_bytes.is_empty() || double || hmac_key.is_some() || !also.is_empty() {
+        return Err(SearchError::InvalidConfig(
+            "the GPU does not support prefix bytes, double hashing, HMAC or further algorithms",
+        ));
+    }
+    if seed.is_some() || checks.iter().any(|check| check.max.is_some()) {
+        return Err(SearchError::InvalidConfig(
+            "the GPU does not support random candidates or a maximum digest",
+        ));
+    }
+
+    let stop = CancelToken::flag(cancel);
+    let end = end.unwrap_or(usize::MAX);
+    let progress = Arc::new(Progress::new(Box::new([AtomicUsize::new(start)]), 1));
+    let (result_tx, result_rx) = results_channel(channel_capacity, overflow, &stop, &progress);
+    // OpenCL handles are set up on the thread using them, which reports back whether that worked
+    let (setup_tx, setup_rx) = bounded(1);
+
+    let th_stop = stop.clone();
+    let th_progress = progress.clone();
+    let handle = thread::spawn(move || {
+        let device = match Device::new(&checks, width, big_endian) {
+            Ok(device) => {
+                let _ = setup_tx.send(Ok(()));
+                device
+            }
+            Err(e) => {
+                let _ = setup_tx.send(Err(e));
+                return;
+            }
+        };
+        info!("hashing {start}..{end} on the GPU in batches of {GPU_BATCH_SIZE}");
+        let mut tester = Tester::<Sha256>::new(&checks, &[], false, None, &[]);
+        let mut results_buf = Vec::new();
+        let mut hits = Vec::new();
+        let mut input = [0; MAX_ENCODED_SIZE];
+        let mut batch_start = start;
+        while !th_stop.load(Ordering::Relaxed) && batch_start < end {
+            let batch_len = GPU_BATCH_SIZE.min(end - batch_start);
+            if let Err(e) = device.run(batch_start, batch_len, &mut hits) {
+                error!("the GPU failed at {batch_start}: {e}");
+                return;
+            }
+            // the work items report their hits in whatever order they finish
+            hits.sort_unstable();
+            for &offset in &hits {
+                let n = batch_start + offset as usize;
+                let candidate = encoding.encode(n, width, &mut input);
+                match tester.test(candidate) {
+                    Some(check) => {
+                        results_buf.push(tester.found(n, n - start + 1, check, candidate))
+                    }
+                    None => warn!("the GPU reported {n}, which does not pass on the CPU"),
+                }
+            }
+
+            if !result_tx.finish_batch(0, batch_len, &mut results_buf) {
+                debug!("GPU search stopped while sending results");
+                return;
+            }
+            batch_start += batch_len;
+            th_progress.frontiers[0].store(batch_start, Ordering::Release);
+        }
+        if batch_start >= end {
+            debug!("GPU search finished the range");
+            th_progress.finish();
+        } else {
+            debug!("GPU search stopped at {batch_start}");
+        }
+    });
+
+    match setup_rx.recv() {
+        Ok(Ok(())) => Ok(Workers {
+            handles: vec![handle],
+            results: result_rx,
+            stop,
+            progress,
+        }),
+        Ok(Err(e)) => Err(e.into()),
+        Err(_) => Err(SearchError::AllWorkersDead),
+    }
+}
+
+/// Compiled kernel along with the buffers it writes to
+struct Device {
+    kernel: Kernel,
+    count: Buffer<u32>,
+    hits: Buffer<u32>,
+}
+
+impl Device {
+    fn new(checks: &[Check], width: usize, big_endian: bool) -> ocl::Result<Self> {
+        let pro_que = ProQue::builder().src(KERNEL).dims(GPU_BATCH_SIZE).build()?;
+        // OpenCL buffers cannot be empty, and no checks pass nothing just like `NEVER`
+        let check_words: Vec<u32> = if checks.is_empty() {
+            Check::NEVER.sha256_words().to_vec()
+        } else {
+            checks.iter().flat_map(Check::sha256_words).collect()
+        };
+        let checks = Buffer::builder()
+            .queue(pro_que.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(check_words.len())
+            .copy_host_slice(&check_words)
+            .build()?;
+        let count = pro_que.buffer_builder().len(1).build()?;
+        let hits = pro_que.create_buffer()?;
+        let kernel = pro_que
+            .kernel_builder("search")
+            .arg(0u64)
+            .arg(0u32)
+            .arg(width as u32)
+            .arg(big_endian as u32)
+            .arg((check_words.len() / 16) as u32)
+            .arg(&checks)
+            .arg(&count)
+            .arg(&hits)
+            .build()?;
+        Ok(Self {
+            kernel,
+            count,
+            hits,
+        })
+    }
+
+    /// Hashes the `len` candidates from `start` on, replacing `hits` with the offsets of
+    /// the ones that passed a check
+    fn run(&self, start: usize, len: usize, hits: &mut Vec<u32>) -> ocl::Result<()> {
+        self.count.write(&[0][..]).enq()?;
+        self.kernel.set_arg(0, start as u64)?;
+        self.kernel.set_arg(1, len as u32)?;
+        // SAFETY: the kernel only writes within `hits`, which has room for every work item
+        unsafe {
+            self.kernel.cmd().global_work_size(len).enq()?;
+        }
+        let mut count = [0];
+        self.count.read(&mut count[..]).enq()?;
+        hits.resize(count[0] as usize, 0);
+        if !hits.is_empty() {
+            self.hits.read(&mut hits[..]).enq()?;
+        }
+        Ok(())
+    }
+}
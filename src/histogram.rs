@@ -0,0 +1,76 @@
+use crate::{
+    encoding::MAX_ENCODED_SIZE,
+    worker::{core_for, pin_to, Candidates, Field, Tester},
+    CancelToken, Config, Direction, SearchError,
+};
+use sha2::{
+    digest::{core_api::BlockSizeUser, FixedOutputReset},
+    Digest,
+};
+use std::thread;
+
+/// Hashes the range of `config` with `D` and counts the digests
+/// by how many zero nibbles they start or end with
+///
+/// Element `k` of the result is the number of digests with exactly `k` zero nibbles,
+/// up to all of them zero.
+/// Without an end the range is hashed until [`Config::cancel`] stops it,
+/// which takes effect within one batch.
+/// Workers count into their own histograms, which are added up once they all exited.
+/// The checks and channel of `config` do not apply.
+/// Fails if `config` does not [validate](Config::validate),
+/// or has a seed, further algorithms or `resume`.
+pub fn zero_histogram<D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send>(
+    config: &Config,
+    direction: Direction,
+) -> Result<Vec<u64>, SearchError> {
+    config.validate_for::<D>(&[Field::Seed, Field::Also, Field::Resume])?;
+    let stop = CancelToken::flag(config.cancel.clone());
+    let candidates = Candidates::new(config.start, config.end, config.step, config.descending);
+    let nibbles = <D as Digest>::output_size() * 2;
+    let batch_size = config.batch_size;
+
+    let histograms = thread::scope(|scope| {
+        let handles = (0..config.workers).map(|i| {
+            let stop = &stop;
+            let candidates = &candidates;
+            let core = core_for(config.pin, config.numa, i);
+
+            scope.spawn(move || {
+                pin_to(core);
+                let mut tester = Tester::<D>::new(
+                    &[],
+                    &config.prefix_bytes,
+                    config.double,
+                    config.hmac_key.as_deref(),
+                    &[],
+                );
+                let mut counts = vec![0; nibbles + 1];
+                let mut input = [0; MAX_ENCODED_SIZE];
+                let first_batch = batch_size.saturating_mul(i);
+                candidates.scan(first_batch, batch_size, config.workers, stop, |batch, _| {
+                    for position in batch {
+                        let n = candidates.at(position);
+                        let input = config.encoding.encode(n, config.width, &mut input);
+                        counts[tester.zero_nibbles(input, direction)] += 1;
+                    }
+                    true
+                });
+                counts
+            })
+        });
+        handles
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().expect("histogram worker panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    Ok(histograms
+        .into_iter()
+        .reduce(|mut total, counts| {
+            total.iter_mut().zip(counts).for_each(|(t, c)| *t += c);
+            total
+        })
+        .unwrap_or_default())
+}
@@ -0,0 +1,355 @@
+use crate::{
+    check::{to_words, Check, Words},
+    MAX_DIGEST_SIZE, MAX_ENCODED_SIZE,
+};
+
+/// Candidates hashed together, as many as 32 bit words fit in a 512 bit register
+pub(crate) const LANES: usize = 16;
+
+/// One 32 bit word of every lane
+type Lanes = [u32; LANES];
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const BLOCK_SIZE: usize = 64;
+
+/// Longest rest of a message the lanes hash, the part of the prefix past its whole blocks,
+/// the longest candidate and the padding
+const MAX_TAIL: usize = 2 * BLOCK_SIZE;
+
+/// Instructions the lanes are compiled to
+#[derive(Clone, Copy)]
+enum Simd {
+    /// whatever the target has, which loses against the SHA extensions, for testing
+    #[cfg(test)]
+    Portable,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+}
+
+impl Simd {
+    /// Widest vectors the CPU has, if they beat hashing one candidate at a time,
+    /// which `cargo bench -- lanes` compares
+    fn detect() -> Option<Self> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx512f") {
+                return Some(Self::Avx512);
+            }
+            if is_x86_feature_detected!("avx2") {
+                return Some(Self::Avx2);
+            }
+        }
+        None
+    }
+
+    fn compress(self, state: &mut [Lanes; 8], block: &[Lanes; 16]) {
+        match self {
+            #[cfg(test)]
+            Self::Portable => compress(state, block),
+            // SAFETY: only detected where the CPU supports the instructions
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { compress_avx2(state, block) },
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512 => unsafe { compress_avx512(state, block) },
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn compress_avx2(state: &mut [Lanes; 8], block: &[Lanes; 16]) {
+    compress(state, block)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+fn compress_avx512(state: &mut [Lanes; 8], block: &[Lanes; 16]) {
+    compress(state, block)
+}
+
+/// Word `i` of every lane computed by `f`, written as a loop the compiler vectorizes
+#[inline(always)]
+fn lanes(f: impl Fn(usize) -> u32) -> Lanes {
+    let mut lanes = [0; LANES];
+    for (i, lane) in lanes.iter_mut().enumerate() {
+        *lane = f(i);
+    }
+    lanes
+}
+
+/// SHA-256 compression of one block in every lane, inlined into the functions that pick
+/// the instructions it is compiled to
+#[inline(always)]
+fn compress(state: &mut [Lanes; 8], block: &[Lanes; 16]) {
+    let mut w = [[0; LANES]; 64];
+    w[..16].copy_from_slice(block);
+    for t in 16..64 {
+        let (w2, w7, w15, w16) = (w[t - 2], w[t - 7], w[t - 15], w[t - 16]);
+        w[t] = lanes(|i| {
+            let s0 = w15[i].rotate_right(7) ^ w15[i].rotate_right(18) ^ (w15[i] >> 3);
+            let s1 = w2[i].rotate_right(17) ^ w2[i].rotate_right(19) ^ (w2[i] >> 10);
+            s1.wrapping_add(w7[i]).wrapping_add(s0).wrapping_add(w16[i])
+        });
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (k, w) in K.iter().zip(&w) {
+        let t1 = lanes(|i| {
+            let s1 = e[i].rotate_right(6) ^ e[i].rotate_right(11) ^ e[i].rotate_right(25);
+            let ch = (e[i] & f[i]) ^ (!e[i] & g[i]);
+            h[i].wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(w[i])
+        });
+        let t2 = lanes(|i| {
+            let s0 = a[i].rotate_right(2) ^ a[i].rotate_right(13) ^ a[i].rotate_right(22);
+            let maj = (a[i] & b[i]) ^ (a[i] & c[i]) ^ (b[i] & c[i]);
+            s0.wrapping_add(maj)
+        });
+        h = g;
+        g = f;
+        f = e;
+        e = lanes(|i| d[i].wrapping_add(t1[i]));
+        d = c;
+        c = b;
+        b = a;
+        a = lanes(|i| t1[i].wrapping_add(t2[i]));
+    }
+    for (word, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = lanes(|i| word[i].wrapping_add(added[i]));
+    }
+}
+
+/// State after absorbing the whole blocks of `message`, along with the bytes left over
+fn midstate(message: &[u8]) -> ([u32; 8], &[u8]) {
+    let mut state = INITIAL_STATE.map(|word| [word; LANES]);
+    let mut blocks = message.chunks_exact(BLOCK_SIZE);
+    for block in blocks.by_ref() {
+        let block = array_of(|t| [be_word(block, t); LANES]);
+        compress(&mut state, &block);
+    }
+    (state.map(|word| word[0]), blocks.remainder())
+}
+
+fn array_of<T, const N: usize>(f: impl FnMut(usize) -> T) -> [T; N] {
+    std::array::from_fn(f)
+}
+
+fn be_word(bytes: &[u8], t: usize) -> u32 {
+    u32::from_be_bytes(
+        bytes[t * 4..t * 4 + 4]
+            .try_into()
+            .expect("words are 4 bytes"),
+    )
+}
+
+/// SHA-256 of [`LANES`] candidates of the same length at once, after the same prefix bytes,
+/// then of the digests once more with the second round of doubling or HMAC
+pub(crate) struct Sha256Lanes {
+    simd: Simd,
+    /// state after the whole blocks of the prefix
+    midstate: [u32; 8],
+    /// prefix bytes past its whole blocks, which start every tail
+    rest: Box<[u8]>,
+    prefix_len: usize,
+    /// state after the whole blocks the digests are hashed after and the number of their bytes,
+    /// if there is a second round
+    outer: Option<([u32; 8], usize)>,
+    /// candidate length `tails` are laid out for
+    len: usize,
+    /// rest of the prefix, candidate and padding of every lane
+    tails: [[u8; MAX_TAIL]; LANES],
+    blocks: usize,
+    digests: [Lanes; 8],
+}
+
+impl Sha256Lanes {
+    /// Lanes hashing `prefix` followed by the candidates, the digests then being hashed
+    /// again after `outer`, if the CPU has vectors wide enough to beat hashing one at a time
+    ///
+    /// `outer` has to be whole blocks, like the padded key of HMAC or nothing when doubling.
+    pub(crate) fn new(prefix: &[u8], outer: Option<&[u8]>) -> Option<Self> {
+        Self::with(Simd::detect()?, prefix, outer)
+    }
+
+    fn with(simd: Simd, prefix: &[u8], outer: Option<&[u8]>) -> Option<Self> {
+        let outer = match outer {
+            Some(outer) if outer.len() % BLOCK_SIZE != 0 => return None,
+            Some(outer) => Some((midstate(outer).0, outer.len())),
+            None => None,
+        };
+        let (midstate, rest) = midstate(prefix);
+        let mut lanes = Self {
+            simd,
+            midstate,
+            rest: rest.into(),
+            prefix_len: prefix.len(),
+            outer,
+            len: usize::MAX,
+            tails: [[0; MAX_TAIL]; LANES],
+            blocks: 0,
+            digests: [[0; LANES]; 8],
+        };
+        lanes.lay_out(0);
+        Some(lanes)
+    }
+
+    /// Pads the tails for candidates of `len` bytes
+    fn lay_out(&mut self, len: usize) {
+        let end = self.rest.len() + len;
+        self.blocks = (end + 9).div_ceil(BLOCK_SIZE);
+        let bits = ((self.prefix_len + len) as u64 * 8).to_be_bytes();
+        for tail in &mut self.tails {
+            tail[..self.rest.len()].copy_from_slice(&self.rest);
+            tail[end..].fill(0);
+            tail[end] = 0x80;
+            tail[self.blocks * BLOCK_SIZE - 8..][..8].copy_from_slice(&bits);
+        }
+        self.len = len;
+    }
+
+    /// Puts `candidate` into lane `lane`, failing if it is longer than [`MAX_ENCODED_SIZE`]
+    /// or its length differs from the candidate in lane 0, which sets the length
+    pub(crate) fn set(&mut self, lane: usize, candidate: &[u8]) -> bool {
+        if candidate.len() != self.len {
+            if lane != 0 || candidate.len() > MAX_ENCODED_SIZE {
+                return false;
+            }
+            self.lay_out(candidate.len());
+        }
+        self.tails[lane][self.rest.len()..][..self.len].copy_from_slice(candidate);
+        true
+    }
+
+    /// Hashes the candidates of every lane
+    pub(crate) fn hash(&mut self) {
+        let mut state = self.midstate.map(|word| [word; LANES]);
+        for block in 0..self.blocks {
+            let words = array_of(|t| lanes(|i| be_word(&self.tails[i][block * BLOCK_SIZE..], t)));
+            self.simd.compress(&mut state, &words);
+        }
+        if let Some((outer, outer_len)) = self.outer {
+            let bits = ((outer_len + 32) as u64 * 8).to_be_bytes();
+            let mut padding = [0; 32];
+            padding[0] = 0x80;
+            padding[24..].copy_from_slice(&bits);
+            let block = array_of(|t| match t {
+                0..8 => state[t],
+                _ => [be_word(&padding, t - 8); LANES],
+            });
+            state = outer.map(|word| [word; LANES]);
+            self.simd.compress(&mut state, &block);
+        }
+        self.digests = state;
+    }
+
+    /// Whether the digest of lane `lane` passes any of `checks`
+    #[inline]
+    pub(crate) fn passes(&self, lane: usize, checks: &[Check]) -> bool {
+        let words = self.words(lane);
+        checks.iter().any(|check| check.passes(&words, 32 / 8))
+    }
+
+    /// Digest of lane `lane` as words to check
+    #[inline]
+    fn words(&self, lane: usize) -> Words {
+        let mut bytes = [0; MAX_DIGEST_SIZE];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(&self.digests) {
+            chunk.copy_from_slice(&word[lane].to_be_bytes());
+        }
+        to_words(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    /// Digests of the lanes of `lanes` after setting them to `candidates`
+    fn digests(lanes: &mut Sha256Lanes, candidates: &[Vec<u8>; LANES]) -> Vec<Vec<u8>> {
+        for (lane, candidate) in candidates.iter().enumerate() {
+            assert!(lanes.set(lane, candidate));
+        }
+        lanes.hash();
+        (0..LANES)
+            .map(|lane| {
+                let words = lanes.words(lane);
+                words
+                    .iter()
+                    .flat_map(|word| word.to_ne_bytes())
+                    .take(32)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lanes_match_single_digests() {
+        let simd = Simd::detect().into_iter().chain([Simd::Portable]);
+        for simd in simd {
+            // prefixes within the first block, filling it and spilling into the next
+            for prefix_len in [0, 5, 40, 64, 70] {
+                let prefix: Vec<u8> = (0..prefix_len as u8).collect();
+                let mut lanes = Sha256Lanes::with(simd, &prefix, None).unwrap();
+                let mut double = Sha256Lanes::with(simd, &prefix, Some(&[])).unwrap();
+                for len in [1, 8, 20, MAX_ENCODED_SIZE] {
+                    let candidates = array_of(|i| vec![i as u8 * 7 + len as u8; len]);
+                    let single: Vec<_> = candidates
+                        .iter()
+                        .map(|candidate| Sha256::digest([&prefix[..], candidate].concat()))
+                        .collect();
+                    let expected: Vec<_> = single.iter().map(|digest| digest.to_vec()).collect();
+                    assert_eq!(digests(&mut lanes, &candidates), expected);
+                    let expected: Vec<_> = single
+                        .iter()
+                        .map(|digest| Sha256::digest(digest).to_vec())
+                        .collect();
+                    assert_eq!(digests(&mut double, &candidates), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn second_round_after_whole_blocks() {
+        let outer = [0x5c; 64];
+        let mut lanes = Sha256Lanes::with(Simd::Portable, b"inner", Some(&outer)).unwrap();
+        let candidates = array_of(|i| (i as u64).to_le_bytes().to_vec());
+        let expected: Vec<_> = candidates
+            .iter()
+            .map(|candidate| {
+                let inner = Sha256::digest([&b"inner"[..], candidate].concat());
+                Sha256::digest([&outer[..], &inner].concat()).to_vec()
+            })
+            .collect();
+        assert_eq!(digests(&mut lanes, &candidates), expected);
+        assert!(Sha256Lanes::with(Simd::Portable, &[], Some(&[0; 10])).is_none());
+    }
+
+    #[test]
+    fn lanes_take_one_length_at_a_time() {
+        let mut lanes = Sha256Lanes::with(Simd::Portable, &[], None).unwrap();
+        assert!(lanes.set(0, b"123"));
+        assert!(!lanes.set(1, b"1234"));
+        assert!(lanes.set(0, b"1234"));
+        assert!(lanes.set(1, b"1234"));
+        assert!(!lanes.set(0, &[0; MAX_ENCODED_SIZE + 1]));
+    }
+}
@@ -0,0 +1,289 @@
+//! Brute-force search for integers whose hash has a desired shape,
+//! e.g. a number of trailing zeroes
+mod algo;
+mod best;
+#[cfg(feature = "tokio")]
+mod bridge;
+mod check;
+mod encoding;
+mod error;
+#[cfg(feature = "opencl")]
+mod gpu;
+mod histogram;
+mod lanes;
+mod lines;
+#[cfg(feature = "rayon")]
+mod pool;
+mod random;
+mod sorted;
+mod worker;
+
+pub use algo::Algo;
+pub use best::spawn_best_workers;
+#[cfg(feature = "tokio")]
+pub use bridge::{search_async, AsyncResults};
+pub use check::{Check, Direction, HexPattern, MAX_DIGEST_SIZE};
+pub use encoding::{Encoding, HexBytes, MAX_ENCODED_SIZE, MAX_WIDTH};
+pub use error::SearchError;
+#[cfg(feature = "opencl")]
+pub use gpu::{spawn_gpu_workers, GPU_BATCH_SIZE};
+pub use histogram::zero_histogram;
+pub use lines::{spawn_line_reader, spawn_line_workers, LineBatch};
+#[cfg(feature = "rayon")]
+pub use pool::spawn_pool_workers;
+pub use sorted::SortedResults;
+pub use worker::{
+    adaptive_batch_size, spawn_workers, verify, CancelToken, Config, DigestBytes, Found, Overflow,
+    Progress, WorkerStats, Workers, DEFAULT_BATCH_SIZE, DEFAULT_CHANNEL_CAPACITY,
+    UNBOUNDED_CHANNEL,
+};
+
+use log::{info, warn};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufWriter, Write},
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+/// Finds `count` integers whose SHA-256 hash ends with `num_zeros` zero nibbles
+/// using `workers` threads, yielding them as they are found
+///
+/// The workers are stopped once `count` results were yielded or the iterator is dropped,
+/// a `count` of 0 yields results until the range is exhausted.
+/// Fails if `num_zeros` is 0, which would match every candidate like `-N 0` would,
+/// or the digest has fewer than `num_zeros` nibbles.
+pub fn search(
+    num_zeros: usize,
+    count: usize,
+    workers: usize,
+) -> Result<SearchResults, SearchError> {
+    let digest_size = Algo::Sha256.digest_size();
+    if num_zeros == 0 || num_zeros > digest_size * 2 {
+        return Err(SearchError::InvalidNumZeros {
+            num_zeros,
+            max: digest_size * 2,
+        });
+    }
+    let check = Check::zeros(num_zeros, digest_size, Direction::Trailing);
+    Ok(SearchResults {
+        workers: Some(Algo::Sha256.spawn_workers(Config::new(check, workers))?),
+        algo: Algo::Sha256,
+        remaining: (count > 0).then_some(count),
+        started: Instant::now(),
+    })
+}
+
+/// Like [`search`], but writes every result to `out` as a `n: hash` line
+///
+/// Results are buffered while more of them are waiting, so writing many is cheap,
+/// and flushed once the search catches up with them.
+pub fn search_into(
+    num_zeros: usize,
+    count: usize,
+    workers: usize,
+    out: &mut impl Write,
+) -> Result<(), SearchError> {
+    let mut out = BufWriter::new(out);
+    let mut results = search(num_zeros, count, workers)?;
+    while let Some(result) = results.next() {
+        let found = result?;
+        writeln!(out, "{}: {}", found.n, found.digest)?;
+        if results
+            .workers
+            .as_ref()
+            .is_none_or(|w| w.results().is_empty())
+        {
+            out.flush()?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Candidate found by a search along with its digest, as yielded by [`SearchResults`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Match {
+    /// The candidate integer, or line number of a candidate line
+    pub n: u64,
+    pub digest: DigestBytes,
+    /// Algorithm the digest was computed with
+    pub algo: Algo,
+}
+
+impl Match {
+    /// `found` with its digest computed with `algo`
+    pub fn new(found: &Found, algo: Algo) -> Self {
+        Self {
+            n: found.n as u64,
+            digest: found.hash,
+            algo,
+        }
+    }
+
+    /// The digest as lowercase hex
+    pub fn hash_hex(&self) -> String {
+        self.digest.to_string()
+    }
+}
+
+/// Iterator over the results of a search, receiving each one as it is requested
+///
+/// Ends once the workers exhaust the range or are cancelled,
+/// after yielding [`SearchError::AllWorkersDead`] if they exited otherwise.
+/// Dropping it stops the workers.
+pub struct SearchResults {
+    workers: Option<Workers>,
+    algo: Algo,
+    /// results left to yield before stopping the workers, if limited
+    remaining: Option<usize>,
+    started: Instant,
+}
+
+impl SearchResults {
+    /// Iterates over every result of `workers`, which hash with `algo`
+    pub fn new(workers: Workers, algo: Algo) -> Self {
+        Self {
+            workers: Some(workers),
+            algo,
+            remaining: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Yields every result along with the time from the start of the search
+    /// until it was received, for plotting difficulty against time
+    pub fn timed(self) -> TimedResults {
+        TimedResults(self)
+    }
+}
+
+impl Iterator for SearchResults {
+    type Item = Result<Match, SearchError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let workers = self.workers.as_ref()?;
+        let Ok(found) = workers.results().recv() else {
+            let (exhausted, stopped) = (workers.progress().exhausted(), workers.stopped());
+            self.workers = None;
+            if exhausted {
+                info!("search ended, the range was exhausted");
+            } else if stopped {
+                info!("search cancelled");
+            } else {
+                warn!("search ended, all workers died");
+            }
+            return (!exhausted && !stopped).then_some(Err(SearchError::AllWorkersDead));
+        };
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+            if *remaining == 0 {
+                info!("search ended, all results were found");
+                // dropping the workers stops them
+                self.workers = None;
+            }
+        }
+        Some(Ok(Match::new(&found, self.algo)))
+    }
+}
+
+/// Results of a search along with when they were received, see [`SearchResults::timed`]
+pub struct TimedResults(SearchResults);
+
+impl Iterator for TimedResults {
+    type Item = Result<(Match, Duration), SearchError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.0.next()?;
+        let elapsed = self.0.started.elapsed();
+        Some(result.map(|found| (found, elapsed)))
+    }
+}
+
+/// Runs a search with `algo`, calling `f` with every candidate
+/// that passes the check along with its digest, as they are found
+///
+/// Returning [`ControlFlow::Break`] from `f` stops the workers and waits for them to exit.
+/// Returns `Break` if `f` or [`Config::cancel`] stopped the search
+/// and `Continue` if the range was exhausted.
+/// Fails if `config` is invalid or the workers died before exhausting the range.
+pub fn search_with<F: FnMut(usize, &[u8]) -> ControlFlow<()>>(
+    algo: Algo,
+    config: Config,
+    mut f: F,
+) -> Result<ControlFlow<()>, SearchError> {
+    let workers = algo.spawn_workers(config)?;
+    // returning early drops the workers, which stops them
+    for found in workers.results() {
+        if f(found.n, found.hash.as_bytes()).is_break() {
+            info!("search stopped by the callback");
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    if workers.stopped() {
+        info!("search cancelled");
+        return Ok(ControlFlow::Break(()));
+    }
+    if !workers.progress().exhausted() {
+        warn!("search ended, all workers died");
+        return Err(SearchError::AllWorkersDead);
+    }
+    info!("search ended, the range was exhausted");
+    Ok(ControlFlow::Continue(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_num_zeros_is_rejected() {
+        let err = search(0, 1, 1).err().expect("0 zeros match everything");
+        assert!(matches!(
+            err,
+            SearchError::InvalidNumZeros { num_zeros: 0, .. }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "zero count must be at least 1, otherwise every number matches"
+        );
+    }
+
+    #[test]
+    fn num_zeros_up_to_the_digest_size() {
+        // dropping the results stops the workers straight away
+        assert!(search(64, 1, 1).is_ok());
+        assert!(matches!(
+            search(65, 1, 1),
+            Err(SearchError::InvalidNumZeros {
+                num_zeros: 65,
+                max: 64
+            })
+        ));
+    }
+
+    #[test]
+    fn no_workers_is_rejected() {
+        assert!(matches!(
+            search(1, 1, 0),
+            Err(SearchError::InvalidConfig("at least 1 worker is required"))
+        ));
+        let config = Config::new(Check::EMPTY, 0);
+        assert!(Algo::Sha256.spawn_workers(config).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn match_round_trip() {
+        use sha2::{Digest, Sha256};
+
+        let found = search(2, 1, 1).unwrap().next().unwrap().unwrap();
+        let json = serde_json::to_string(&found).unwrap();
+        assert_eq!(serde_json::from_str::<Match>(&json).unwrap(), found);
+        // the hex the hash used to be printed as, of `n` as 8 little-endian bytes
+        let hash = Sha256::digest(found.n.to_le_bytes());
+        assert_eq!(found.hash_hex(), format!("{hash:x}"));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    worker::{
+        core_for, pin_to, results_channel, Field, Tester, DEFAULT_BATCH_SIZE, STOP_POLL_INTERVAL,
+    },
+    CancelToken, Config, Found, Progress, SearchError, Workers,
+};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError};
+use sha2::{
+    digest::{core_api::BlockSizeUser, FixedOutputReset},
+    Digest,
+};
+use std::{
+    io::BufRead,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Candidate lines along with their line numbers, counted from 1
+pub type LineBatch = Vec<(usize, Vec<u8>)>;
+
+/// Spawns a thread reading lines from `input` and sending them
+/// in batches of [`DEFAULT_BATCH_SIZE`], without their line terminators
+///
+/// The thread exits at the end of input, on a read error,
+/// or once it fails to send because the receiver was dropped.
+pub fn spawn_line_reader(
+    mut input: impl BufRead + Send + 'static,
+) -> (Receiver<LineBatch>, JoinHandle<Result<(), SearchError>>) {
+    // a few batches of read-ahead keep workers busy without buffering the whole input
+    const QUEUED_BATCHES: usize = 16;
+    let (lines_tx, lines_rx) = bounded(QUEUED_BATCHES);
+    let handle = thread::spawn(move || {
+        let mut n = 1;
+        let mut batch = Vec::with_capacity(DEFAULT_BATCH_SIZE);
+        loop {
+            let mut line = Vec::new();
+            if input.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            if line.ends_with(b"\n") {
+                line.pop();
+                if line.ends_with(b"\r") {
+                    line.pop();
+                }
+            }
+            batch.push((n, line));
+            n += 1;
+            if batch.len() == DEFAULT_BATCH_SIZE {
+                let full = std::mem::replace(&mut batch, Vec::with_capacity(DEFAULT_BATCH_SIZE));
+                if lines_tx.send(full).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        if !batch.is_empty() {
+            // the receiver may already be gone, then nobody needs the rest
+            let _ = lines_tx.send(batch);
+        }
+        Ok(())
+    });
+    (lines_rx, handle)
+}
+
+/// Spawns worker threads hashing lines received from `lines` with `D`
+/// and sending those that pass the check to the results channel
+///
+/// The range, encoding, width and batch size of `config` do not apply,
+/// lines are hashed in the batches they were sent in.
+/// Workers exit once `lines` is disconnected and drained.
+/// Fails if `config` does not [validate](Config::validate),
+/// or has a step, descending candidates, a seed or `resume`.
+/// Lines are handed out as workers become free, so nothing is known to be scanned
+/// until then and [`crate::SortedResults`] holds back every result until the end.
+pub fn spawn_line_workers<D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send + 'static>(
+    config: Config,
+    lines: Receiver<LineBatch>,
+) -> Result<Workers, SearchError> {
+    config.validate_for::<D>(&[Field::Sequence, Field::Seed, Field::Resume])?;
+    let stop = CancelToken::flag(config.cancel.clone());
+    let progress = Arc::new(Progress::new(
+        (0..config.workers).map(|_| AtomicUsize::new(0)).collect(),
+        config.workers,
+    ));
+    let (result_tx, result_rx) =
+        results_channel(config.channel_capacity, config.overflow, &stop, &progress);
+    let config = Arc::new(config);
+
+    let handles = (0..config.workers).map(|i| {
+        let th_result_tx = result_tx.clone();
+        let th_stop = stop.clone();
+        let th_progress = progress.clone();
+        let th_lines = lines.clone();
+        let th_config = config.clone();
+        let core = core_for(config.pin, config.numa, i);
+
+        thread::spawn(move || {
+            pin_to(core);
+            let mut tester = Tester::<D>::new(
+                &th_config.checks,
+                &th_config.prefix_bytes,
+                th_config.double,
+                th_config.hmac_key.as_deref(),
+                &th_config.also,
+            );
+            let mut results_buf = Vec::new();
+            while !th_stop.load(Ordering::Relaxed) {
+                // input may stall indefinitely, so the stop flag is polled meanwhile
+                let batch = match th_lines.recv_timeout(STOP_POLL_INTERVAL) {
+                    Ok(batch) => batch,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        th_progress.finish();
+                        return;
+                    }
+                };
+                for (n, line) in &batch {
+                    if let Some(check) = tester.test(line) {
+                        results_buf.push(Found {
+                            line: Some(String::from_utf8_lossy(line).into_owned()),
+                            ..tester.found(*n, *n, check, line)
+                        })
+                    }
+                }
+
+                if !th_result_tx.finish_batch(i, batch.len(), &mut results_buf) {
+                    return;
+                }
+            }
+        })
+    });
+    Ok(Workers {
+        handles: handles.collect(),
+        results: result_rx,
+        stop,
+        progress,
+    })
+}
@@ -1,105 +1,2421 @@
 use argh::FromArgs;
-use crossbeam_channel::{unbounded, Receiver};
-use sha2::{digest::generic_array::GenericArray, Digest, Sha256};
-use std::{mem::transmute, thread};
+use base64::{
+    display::Base64Display,
+    engine::general_purpose::{STANDARD, URL_SAFE},
+};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError};
+use hashsearch::{
+    adaptive_batch_size, spawn_line_reader, Algo, CancelToken, Check, Config, DigestBytes,
+    Direction, Encoding, Found, HexBytes, HexPattern, Overflow, Progress, SearchError,
+    SortedResults, Workers, DEFAULT_CHANNEL_CAPACITY, MAX_ENCODED_SIZE, MAX_WIDTH,
+    UNBOUNDED_CHANNEL,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::LevelFilter;
+use memmap2::MmapMut;
+use serde::{Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::RandomState, BTreeSet},
+    fmt,
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, BufWriter, IsTerminal, Write},
+    iter,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How much is written to stderr besides errors
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// nothing
+    Quiet,
+    /// warnings, how the search ended along with a summary, and progress if requested
+    Normal,
+    /// also the work of every worker thread and debug logs
+    Verbose,
+}
+
+impl Verbosity {
+    /// Most detailed log records shown, unless `RUST_LOG` says otherwise
+    fn log_level(self) -> LevelFilter {
+        match self {
+            Self::Quiet => LevelFilter::Error,
+            Self::Normal => LevelFilter::Warn,
+            Self::Verbose => LevelFilter::Debug,
+        }
+    }
+
+    /// Writes `message` to stderr unless quiet
+    fn note(self, message: impl fmt::Display) {
+        if self > Self::Quiet {
+            eprintln!("{message}");
+        }
+    }
+
+    /// Writes `message` to stderr as a warning unless quiet
+    fn warn(self, message: impl fmt::Display) {
+        self.note(format_args!("warning: {message}"));
+    }
+}
+
+/// How results are printed to stdout
+#[derive(Clone, Copy)]
+enum Format {
+    /// `n: hash` lines
+    Plain,
+    /// one JSON object per line
+    Json,
+    /// comma separated values with a header row
+    Csv,
+    /// fixed size records of `n` as 8 little-endian bytes followed by the raw digest
+    Binary,
+    /// MessagePack maps with the raw digest, each after its length as 4 little-endian bytes
+    Msgpack,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "binary" => Ok(Self::Binary),
+            "msgpack" => Ok(Self::Msgpack),
+            _ => Err(format!(
+                "unknown format `{s}`, expected one of: plain, json, csv, binary, msgpack"
+            )),
+        }
+    }
+}
+
+/// Part of the range one of several machines scans, `index` counted from 1
+#[derive(Clone, Copy)]
+struct Shard {
+    index: usize,
+    count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid shard `{s}`, expected i/n with i from 1 to n");
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let index = index.parse().map_err(|_| invalid())?;
+        let count = count.parse().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(invalid());
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl Shard {
+    /// The contiguous part of `start..end` this shard covers,
+    /// shards of the same count splitting it without gaps or overlaps
+    fn range(self, start: usize, end: usize) -> (usize, usize) {
+        let len = (end - start) as u128;
+        let bound = |i: usize| start + (len * i as u128 / self.count as u128) as usize;
+        (bound(self.index - 1), bound(self.index))
+    }
+}
+
+/// Algorithms that all have to pass the check, the first one replacing --algo
+#[derive(Clone)]
+struct AlgoList(Vec<Algo>);
+
+impl FromStr for AlgoList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(Algo::from_str)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// How the search is spread over threads
+#[derive(Clone, Copy)]
+enum Backend {
+    /// dedicated threads striding over the range
+    Threads,
+    /// tasks on a rayon thread pool
+    #[cfg(feature = "rayon")]
+    Rayon,
+    /// SHA-256 on an OpenCL device
+    #[cfg(feature = "opencl")]
+    Gpu,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threads" => Ok(Self::Threads),
+            #[cfg(feature = "rayon")]
+            "rayon" => Ok(Self::Rayon),
+            #[cfg(feature = "opencl")]
+            "gpu" => Ok(Self::Gpu),
+            _ => Err(format!(
+                "unknown backend `{s}`, expected one of: threads{}{}",
+                if cfg!(feature = "rayon") {
+                    ", rayon"
+                } else {
+                    ""
+                },
+                if cfg!(feature = "opencl") {
+                    ", gpu"
+                } else {
+                    ""
+                }
+            )),
+        }
+    }
+}
+
+/// Capacity of the results channel given as `unbounded` or `bounded:<capacity>`
+#[derive(Clone, Copy)]
+struct Channel(usize);
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "unbounded" {
+            return Ok(Self(UNBOUNDED_CHANNEL));
+        }
+        match s.strip_prefix("bounded:").map(str::parse) {
+            Some(Ok(0)) => Err("a bounded channel needs a capacity of at least 1".into()),
+            Some(Ok(capacity)) => Ok(Self(capacity)),
+            _ => Err(format!(
+                "invalid channel `{s}`, expected unbounded or bounded:<capacity>"
+            )),
+        }
+    }
+}
+
+/// Printed form of a result in structured formats, with hashes of type `H`
+/// and the hashed bytes of type `I`
+#[derive(Serialize)]
+struct Record<'a, H, I> {
+    n: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<&'a str>,
+    attempts: usize,
+    hash: H,
+    /// exact bytes hashed, prefix bytes included
+    input: I,
+    algo: Algo,
+    /// hashes of the further --algos
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    also: Vec<AlsoRecord<H>>,
+    /// `-N` target the result counts towards, if there are several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zeros: Option<usize>,
+}
+
+impl<'a, H, I> Record<'a, H, I> {
+    /// `found` with its hashes turned into `H` by `hash` and its hashed bytes into `I` by `input`
+    fn new(
+        found: &'a Found,
+        args: &Args,
+        zeros: Option<usize>,
+        hash: impl Fn(&'a DigestBytes) -> H,
+        input: impl Fn(&'a HexBytes) -> I,
+    ) -> Self {
+        Self {
+            n: found.n,
+            line: found.line.as_deref(),
+            attempts: found.attempts,
+            hash: hash(&found.hash),
+            input: input(&found.input),
+            algo: args.algo,
+            also: args
+                .also()
+                .iter()
+                .zip(&found.also)
+                .map(|(&algo, digest)| AlsoRecord {
+                    algo,
+                    hash: hash(digest),
+                })
+                .collect(),
+            zeros,
+        }
+    }
+}
+
+/// Printed form of a further hash of a result in structured formats
+#[derive(Serialize)]
+struct AlsoRecord<H> {
+    algo: Algo,
+    hash: H,
+}
+
+/// Digest or hashed bytes serialized as raw bytes, for binary structured formats
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// How digests are written in text formats
+#[derive(Clone, Copy)]
+enum HashEncoding {
+    Hex,
+    /// standard base64 alphabet with padding
+    Base64,
+    /// URL and filename safe base64 alphabet with padding
+    Base64Url,
+}
+
+impl FromStr for HashEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "base64" => Ok(Self::Base64),
+            "base64url" => Ok(Self::Base64Url),
+            _ => Err(format!(
+                "unknown hash encoding `{s}`, expected one of: hex, base64, base64url"
+            )),
+        }
+    }
+}
+
+/// Digest printed in the selected encoding, and case and `0x` prefix for hex
+#[derive(Clone, Copy)]
+struct HashText<'a> {
+    digest: &'a DigestBytes,
+    encoding: HashEncoding,
+    uppercase: bool,
+    hex_prefix: bool,
+}
+
+impl<'a> HashText<'a> {
+    /// `digest` as selected by --hash-encoding, --uppercase and --hex-prefix
+    fn new(digest: &'a DigestBytes, args: &Args) -> Self {
+        Self {
+            digest,
+            encoding: args.hash_encoding,
+            uppercase: args.uppercase,
+            hex_prefix: args.hex_prefix,
+        }
+    }
+}
+
+impl fmt::Display for HashText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.digest.as_bytes();
+        if self.hex_prefix && matches!(self.encoding, HashEncoding::Hex) {
+            f.write_str("0x")?;
+        }
+        match self.encoding {
+            HashEncoding::Hex if self.uppercase => write!(f, "{:X}", self.digest),
+            HashEncoding::Hex => write!(f, "{:x}", self.digest),
+            HashEncoding::Base64 => Base64Display::new(bytes, &STANDARD).fmt(f),
+            HashEncoding::Base64Url => Base64Display::new(bytes, &URL_SAFE).fmt(f),
+        }
+    }
+}
+
+impl Serialize for HashText<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Output line with placeholders substituted for every result
+#[derive(Clone)]
+struct Template(Vec<Piece>);
+
+#[derive(Clone)]
+enum Piece {
+    Literal(String),
+    N,
+    Line,
+    /// the hash, in hex of the given case if forced or otherwise in --hash-encoding
+    Hash(Option<bool>),
+    Algo,
+    Attempts,
+    Zeros,
+    Input,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| format!("unclosed placeholder in template `{s}`"))?;
+                    let piece = match name {
+                        "n" => Piece::N,
+                        "line" => Piece::Line,
+                        "hash" => Piece::Hash(None),
+                        "hash:x" => Piece::Hash(Some(false)),
+                        "hash:X" => Piece::Hash(Some(true)),
+                        "algo" => Piece::Algo,
+                        "attempts" => Piece::Attempts,
+                        "zeros" => Piece::Zeros,
+                        "input" => Piece::Input,
+                        _ => {
+                            return Err(format!(
+                                "unknown placeholder `{{{name}}}`, expected one of: \
+                                 {{n}}, {{line}}, {{hash}}, {{hash:x}}, {{hash:X}}, \
+                                 {{algo}}, {{attempts}}, {{zeros}}, {{input}}"
+                            ))
+                        }
+                    };
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    pieces.push(piece);
+                    chars = rest.chars();
+                }
+                '}' => return Err(format!("unmatched `}}` in template `{s}`, write `}}}}`")),
+                c => literal.push(c),
+            }
+        }
+        pieces.push(Piece::Literal(literal));
+        pieces.retain(|piece| !matches!(piece, Piece::Literal(literal) if literal.is_empty()));
+        Ok(Self(pieces))
+    }
+}
+
+impl Template {
+    /// Writes the line for `found`, with an empty `{line}` or `{zeros}` where they do not apply
+    fn write(
+        &self,
+        out: &mut impl Write,
+        args: &Args,
+        found: &Found,
+        zeros: Option<usize>,
+    ) -> io::Result<()> {
+        for piece in &self.0 {
+            match piece {
+                Piece::Literal(literal) => out.write_all(literal.as_bytes())?,
+                Piece::N => write!(out, "{}", found.n)?,
+                Piece::Line => out.write_all(found.line.as_deref().unwrap_or("").as_bytes())?,
+                Piece::Hash(uppercase) => {
+                    let hash = HashText {
+                        encoding: uppercase.map_or(args.hash_encoding, |_| HashEncoding::Hex),
+                        uppercase: uppercase.unwrap_or(args.uppercase),
+                        ..HashText::new(&found.hash, args)
+                    };
+                    write!(out, "{hash}")?
+                }
+                Piece::Algo => write!(out, "{}", args.algo)?,
+                Piece::Attempts => write!(out, "{}", found.attempts)?,
+                Piece::Zeros => {
+                    if let Some(zeros) = zeros {
+                        write!(out, "{zeros}")?
+                    }
+                }
+                Piece::Input => write!(out, "{}", found.input)?,
+            }
+        }
+        writeln!(out)
+    }
+}
 
 #[derive(FromArgs)]
 /// hashsearch
 struct Args {
-    /// number of zeroes desired hash must end with
+    /// number of zeroes desired hash must end with,
+    /// or start with if --leading is set, can be repeated
+    /// to find --count results for each number in one pass
     #[argh(option, short = 'N')]
-    num_zeros: usize,
-    /// desired number of results,
-    /// defaults to 1
+    num_zeros: Vec<usize>,
+    /// number of zero bits desired hash must end with,
+    /// or start with if --leading is set, exclusive with -N
+    #[argh(option)]
+    zero_bits: Option<usize>,
+    /// desired number of results, 0 to search until interrupted,
+    /// the range ends or --timeout passes, defaults to 1
     #[argh(option, short = 'K', default = "1")]
     count: usize,
     /// number of worker threads,
     /// defaults to number of CPU threads
     #[argh(option, short = 'W')]
     workers: Option<usize>,
+    /// hash algorithm: sha256, or sha512, sha1, md5, blake3, sha3-256 or keccak-256
+    /// if built with their features, defaults to sha256
+    #[argh(option, short = 'a', default = "Algo::Sha256")]
+    algo: Algo,
+    /// comma separated algorithms whose hashes must all pass the check, instead of --algo,
+    /// later ones are only hashed for numbers the earlier ones passed
+    #[argh(option)]
+    algos: Option<AlgoList>,
+    /// hex digest the hash must equal exactly,
+    /// exclusive with other requirements
+    #[argh(option)]
+    target: Option<HexBytes>,
+    /// hex digest the hash must not exceed as a big-endian number,
+    /// exclusive with other requirements
+    #[argh(option)]
+    max_hash: Option<HexBytes>,
+    /// match zeroes at the start of the hash instead of the end
+    #[argh(switch)]
+    leading: bool,
+    /// hex digit that -N and --zero-bits look for instead of 0, like f for fffff
+    #[argh(option)]
+    zero_value: Option<HexPattern>,
+    /// hex digits desired hash must start with,
+    /// can be combined with --suffix
+    #[argh(option)]
+    prefix: Option<HexPattern>,
+    /// hex digits desired hash must end with
+    #[argh(option)]
+    suffix: Option<HexPattern>,
+    /// output format: plain, json, csv, binary or msgpack,
+    /// defaults to plain
+    #[argh(option, default = "Format::Plain")]
+    format: Format,
+    /// line printed for every result instead of --format, with placeholders
+    /// {{n}}, {{line}}, {{hash}}, {{hash:x}}, {{hash:X}}, {{algo}}, {{attempts}},
+    /// {{zeros}} and {{input}}, doubled braces for literal ones
+    #[argh(option)]
+    format_template: Option<Template>,
+    /// first number to hash,
+    /// defaults to 1
+    #[argh(option, default = "1")]
+    start: usize,
+    /// number to stop before,
+    /// the search is unbounded if not set
+    #[argh(option)]
+    end: Option<usize>,
+    /// how numbers are serialized before hashing: le, be, decimal or hex,
+    /// defaults to le
+    #[argh(option, default = "Encoding::Le")]
+    encoding: Encoding,
+    /// number of bytes le and be encodings take, from 1 to 16,
+    /// defaults to 8, the search ends at the largest number that fits
+    #[argh(option, default = "size_of::<usize>()")]
+    width: usize,
+    /// number of digits decimal numbers are left-padded to with zeroes,
+    /// e.g. 00012345 for 8, the search ends at the largest number that fits
+    #[argh(option)]
+    pad_width: Option<usize>,
+    /// hash numbers as big-endian bytes, same as --encoding be
+    #[argh(switch)]
+    big_endian: bool,
+    /// hash numbers as their ASCII decimal digits, e.g. the string 12345,
+    /// same as --encoding decimal
+    #[argh(switch)]
+    ascii: bool,
+    /// hex bytes hashed before every number, e.g. a fixed header
+    #[argh(option, default = "HexBytes::default()")]
+    prefix_bytes: HexBytes,
+    /// hex message to mine a nonce for, hashing message || nonce
+    /// with the nonce --width bytes wide, same as --prefix-bytes
+    #[argh(option)]
+    message: Option<HexBytes>,
+    /// hash every digest again before checking it,
+    /// e.g. Bitcoin's double SHA-256
+    #[argh(switch)]
+    double: bool,
+    /// hex key to hash numbers with HMAC instead,
+    /// --prefix-bytes then start the message, if built with the hmac feature
+    #[argh(option)]
+    hmac_key: Option<HexBytes>,
+    /// file to periodically save the lowest unscanned number to, along with the results
+    /// past it that were printed, the search resumes from it if the file exists
+    /// and appends to --output
+    #[argh(option)]
+    checkpoint: Option<PathBuf>,
+    /// binary file holding where every worker is, updated in place every second,
+    /// each worker resumes from it if the file exists, which needs the same
+    /// --start, --batch and -W
+    #[argh(option)]
+    state: Option<PathBuf>,
+    /// print results in ascending order, holding each back
+    /// until all workers have scanned past it
+    #[argh(switch)]
+    sorted: bool,
+    /// number of results buffered before workers wait for them to be printed,
+    /// defaults to 4096
+    #[argh(option)]
+    channel_capacity: Option<usize>,
+    /// results channel: unbounded, where workers never wait at the cost of memory,
+    /// or bounded:<capacity> like --channel-capacity, results arrive in no particular
+    /// order either way unless --sorted is set
+    #[argh(option)]
+    channel: Option<Channel>,
+    /// number of results waiting to be printed at most, after which workers pause,
+    /// or drop the oldest if --drop-oldest is set, like --channel-capacity
+    #[argh(option)]
+    max_pending: Option<usize>,
+    /// drop the oldest waiting result for a new one instead of pausing the workers
+    /// once the results channel is full, counting them in the summary
+    #[argh(switch)]
+    drop_oldest: bool,
+    /// number of candidates a worker hashes between checking in,
+    /// larger is faster for rare results, defaults to about one expected result
+    /// per batch, from 100 to 65536
+    #[argh(option)]
+    batch: Option<usize>,
+    /// how text formats write hashes: hex, base64 or base64url,
+    /// defaults to hex
+    #[argh(option, default = "HashEncoding::Hex")]
+    hash_encoding: HashEncoding,
+    /// print hashes in uppercase hex
+    #[argh(switch)]
+    uppercase: bool,
+    /// print 0x before hex hashes, as Ethereum tooling expects
+    #[argh(switch)]
+    hex_prefix: bool,
+    /// show how many candidates were tried up to each result,
+    /// always included in json output
+    #[argh(switch)]
+    attempts: bool,
+    /// show the exact bytes hashed for each result as hex, prefix bytes included,
+    /// always included in json and msgpack output
+    #[argh(switch)]
+    show_input: bool,
+    /// print only every nth result, e.g. to get a feel for a low -N search,
+    /// all of them still count towards --count and the summary, defaults to 1
+    #[argh(option, default = "1")]
+    sample: usize,
+    /// print how much work each worker thread did when finished,
+    /// and debug logs of the workers
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// print nothing but results, no summary, progress or warnings,
+    /// errors are still reported
+    #[argh(switch)]
+    quiet: bool,
+    /// show the hash rate on stderr every second, as a bar towards the expected
+    /// number of hashes on a terminal
+    #[argh(switch)]
+    progress: bool,
+    /// address to serve Prometheus metrics on while searching, like 127.0.0.1:9184,
+    /// if built with the metrics feature
+    #[argh(option)]
+    metrics_addr: Option<SocketAddr>,
+    /// seconds after which the search stops even if fewer than
+    /// --count results were found, exiting with code 2 unless --count is 0
+    #[argh(option)]
+    timeout: Option<u64>,
+    /// file to write results to instead of stdout,
+    /// created or truncated
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// pin each worker thread to a distinct CPU core
+    #[argh(switch)]
+    pin: bool,
+    /// with --pin, take the cores of one NUMA node before those of the next,
+    /// as reported by Linux
+    #[argh(switch)]
+    numa: bool,
+    /// how work is spread over threads: threads, rayon if built with the rayon feature,
+    /// or gpu for sha256 on an OpenCL device if built with the opencl feature,
+    /// defaults to threads
+    #[argh(option, default = "Backend::Threads")]
+    backend: Backend,
+    /// measure the hash rate for this many seconds instead of searching,
+    /// nothing ever matches
+    #[argh(option)]
+    bench: Option<u64>,
+    /// hash exactly the first n numbers from --start and print every result among them,
+    /// regardless of --count, for a fixed workload to benchmark against
+    #[argh(option)]
+    exact_attempts: Option<usize>,
+    /// hash every nth number from --start, defaults to 1
+    #[argh(option, default = "1")]
+    step: usize,
+    /// count down from --start to --end, or to 0 if there is none
+    #[argh(switch)]
+    descending: bool,
+    /// scan only part i of n of the range, e.g. 2/4 for the second quarter,
+    /// to split a search across machines
+    #[argh(option)]
+    shard: Option<Shard>,
+    /// hash numbers drawn at random from the range instead of counting up,
+    /// numbers may repeat, a bounded range ends after 32 draws per number
+    #[argh(switch)]
+    random: bool,
+    /// seed for --random, which picks and prints one otherwise,
+    /// the same seed and -W draw the same numbers
+    #[argh(option)]
+    seed: Option<u64>,
+    /// print the expected number of hashes to find --count results
+    /// and how long that takes, measured in a short calibration run, instead of searching
+    #[argh(switch)]
+    estimate: bool,
+    /// print every hash that ends with more zeroes, or starts with if --leading is set,
+    /// than any before it instead of searching for -N, until --end, --timeout or Ctrl-C
+    #[argh(switch)]
+    best: bool,
+    /// count how many hashes of the range end with each number of zeroes,
+    /// or start with if --leading is set, until --end or --timeout
+    #[argh(switch)]
+    histogram: bool,
+    /// print the resolved configuration, including the checked bits of the hash,
+    /// and the expected number of hashes, then exit without hashing
+    #[argh(switch)]
+    dry_run: bool,
+    /// hash only this candidate and report whether it passes the check, printing its hash,
+    /// exits with 1 if it does not
+    #[argh(option)]
+    verify: Option<usize>,
+    /// hash lines read from stdin instead of numbers,
+    /// results are numbered by line
+    #[argh(switch)]
+    stdin: bool,
+    /// hash lines read from this file instead of numbers, like --stdin
+    #[argh(option)]
+    input: Option<PathBuf>,
+}
+
+impl Args {
+    /// Verbosity selected by --quiet and --verbose
+    fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, true) => Verbosity::Verbose,
+            (false, false) => Verbosity::Normal,
+        }
+    }
+
+    /// Option naming where lines are hashed from, if they are
+    fn line_source(&self) -> Option<&'static str> {
+        match (self.stdin, &self.input) {
+            (_, Some(_)) => Some("--input"),
+            (true, None) => Some("--stdin"),
+            (false, None) => None,
+        }
+    }
+
+    /// Whether everything below the progress frontier is known to be scanned, which only
+    /// holds for an ascending search over consecutive candidates
+    fn scans_below_frontier(&self) -> bool {
+        #[cfg(feature = "rayon")]
+        if let Backend::Rayon = self.backend {
+            return false;
+        }
+        self.line_source().is_none() && !self.descending && !self.random
+    }
+
+    /// Algorithms of --algos after the first, whose hashes are printed after the first
+    fn also(&self) -> &[Algo] {
+        self.algos.as_ref().map_or(&[], |algos| &algos.0[1..])
+    }
+
+    /// End of the hash that zeroes are counted at
+    fn direction(&self) -> Direction {
+        if self.leading {
+            Direction::Leading
+        } else {
+            Direction::Trailing
+        }
+    }
 }
 
 fn main() {
-    let args: Args = argh::from_env();
-    search(
-        args.num_zeros,
-        args.count,
-        args.workers.unwrap_or_else(num_cpus::get),
-    );
+    let mut args: Args = argh::from_env();
+    env_logger::Builder::new()
+        .filter_level(args.verbosity().log_level())
+        .parse_default_env()
+        .init();
+    if let Some(algos) = &args.algos {
+        args.algo = algos.0[0];
+    }
+    let config = make_config(&args).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1)
+    });
+    if args.dry_run {
+        if let Err(e) = dry_run(&args, &config) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return;
+    }
+    if args.bench.is_some() || args.estimate {
+        let measured = match args.bench {
+            Some(seconds) => bench(&args, config, Duration::from_secs(seconds)),
+            None => estimate(&args, config),
+        };
+        if let Err(e) = measured {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return;
+    }
+    if let Some(n) = args.verify {
+        if let Err(e) = verify(&args, &config, n) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return;
+    }
+    if args.histogram {
+        if let Err(e) = histogram(&args, config) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return;
+    }
+    if args.progress {
+        let expected = expected_hashes(&args, &config);
+        args.verbosity().note(format_args!(
+            "expecting about {} hashes",
+            approximate(expected)
+        ));
+    }
+    let (out, continued) = open_output(&args).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1)
+    });
+    let code = print_results(&args, config, out, continued);
+    if code != 0 {
+        process::exit(code);
+    }
+}
+
+/// Opens the file results are written to, or stdout, buffered
+///
+/// Results are flushed whenever no more are waiting, see [`print_results`].
+///
+/// A search resuming from a checkpoint or state file appends to the file of the run before,
+/// which is reported along with the writer if it was not empty.
+fn open_output(args: &Args) -> Result<(BufWriter<Box<dyn Write>>, bool), String> {
+    let resuming = [&args.checkpoint, &args.state]
+        .into_iter()
+        .flatten()
+        .any(|path| path.exists());
+    let (out, continued): (Box<dyn Write>, _) = match &args.output {
+        Some(path) if resuming => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("failed to open output file {}: {e}", path.display()))?;
+            let continued = file.metadata().is_ok_and(|metadata| metadata.len() > 0);
+            (Box::new(file), continued)
+        }
+        Some(path) => File::create(path)
+            .map(|file| (Box::new(file) as Box<dyn Write>, false))
+            .map_err(|e| format!("failed to create output file {}: {e}", path.display()))?,
+        None => (Box::new(io::stdout().lock()), false),
+    };
+    Ok((BufWriter::new(out), continued))
+}
+
+/// Seed for --random without --seed, printed so the run can be repeated
+fn random_seed(verbosity: Verbosity) -> u64 {
+    let seed = RandomState::new().build_hasher().finish();
+    verbosity.note(format_args!("seed {seed}"));
+    seed
+}
+
+/// Validates search parameters
+fn make_config(args: &Args) -> Result<Config, String> {
+    if args.descending {
+        if args.end.is_some_and(|end| end >= args.start) {
+            return Err("--end must be less than --start with --descending".into());
+        }
+    } else if args.end.is_some_and(|end| end <= args.start) {
+        return Err("--end must be greater than --start".into());
+    }
+    if args.step == 0 {
+        return Err("--step must be at least 1".into());
+    }
+    let sequence = args.step != 1 || args.descending;
+    if sequence
+        && (args.line_source().is_some()
+            || args.random
+            || args.shard.is_some()
+            || args.state.is_some())
+    {
+        return Err(
+            "--step and --descending cannot be combined with --stdin, --input, --random, \
+             --shard or --state"
+                .into(),
+        );
+    }
+    if args.descending
+        && (args.sorted || args.checkpoint.is_some() || args.exact_attempts.is_some())
+    {
+        return Err(
+            "--descending cannot be combined with --sorted, --checkpoint or --exact-attempts"
+                .into(),
+        );
+    }
+    if args.stdin && args.input.is_some() {
+        return Err("--stdin cannot be combined with --input".into());
+    }
+    if let Some(source) = args.line_source() {
+        if args.start != 1
+            || args.end.is_some()
+            || args.shard.is_some()
+            || args.checkpoint.is_some()
+        {
+            return Err(format!(
+                "{source} cannot be combined with --start, --end, --shard or --checkpoint"
+            ));
+        }
+        if args.bench.is_some() {
+            return Err(format!("--bench cannot be combined with {source}"));
+        }
+    }
+    if args.seed.is_some() && !args.random {
+        return Err("--seed requires --random".into());
+    }
+    let lines = args.line_source().is_some();
+    if args.state.is_some() && (lines || args.random || args.best || args.checkpoint.is_some()) {
+        return Err(
+            "--state cannot be combined with --stdin, --input, --random, --best or --checkpoint"
+                .into(),
+        );
+    }
+    if args.random && (lines || args.sorted || args.checkpoint.is_some()) {
+        return Err(
+            "--random cannot be combined with --stdin, --input, --sorted or --checkpoint".into(),
+        );
+    }
+    if args.estimate && (lines || args.bench.is_some()) {
+        return Err("--estimate cannot be combined with --stdin, --input or --bench".into());
+    }
+    if args.histogram && (lines || args.random || args.bench.is_some() || args.estimate) {
+        return Err(
+            "--histogram cannot be combined with --stdin, --input, --random, --bench or --estimate"
+                .into(),
+        );
+    }
+    if args.histogram && args.end.is_none() && args.timeout.is_none() {
+        return Err("--histogram needs --end or --timeout to stop".into());
+    }
+    if args.best
+        && (!args.num_zeros.is_empty()
+            || args.zero_bits.is_some()
+            || args.prefix.is_some()
+            || args.suffix.is_some()
+            || args.target.is_some()
+            || args.max_hash.is_some()
+            || args.algos.is_some())
+    {
+        return Err(
+            "--best cannot be combined with -N, --zero-bits, --prefix, --suffix, --target, \
+             --max-hash or --algos"
+                .into(),
+        );
+    }
+    if args.best
+        && (lines
+            || args.random
+            || args.bench.is_some()
+            || args.estimate
+            || args.histogram
+            || args.verify.is_some())
+    {
+        return Err(
+            "--best cannot be combined with --stdin, --input, --random, --bench, --estimate, \
+             --histogram or --verify"
+                .into(),
+        );
+    }
+    if args.batch == Some(0) {
+        return Err("--batch must be at least 1".into());
+    }
+    if args.sample == 0 {
+        return Err("--sample must be at least 1".into());
+    }
+    #[cfg(feature = "rayon")]
+    if let Backend::Rayon = args.backend {
+        if lines
+            || args.sorted
+            || args.checkpoint.is_some()
+            || args.state.is_some()
+            || args.random
+            || args.pin
+            || args.numa
+        {
+            return Err(
+                "--backend rayon cannot be combined with --stdin, --input, --sorted, \
+                 --checkpoint, --state, --random, --pin or --numa"
+                    .into(),
+            );
+        }
+    }
+    #[cfg(feature = "opencl")]
+    if let Backend::Gpu = args.backend {
+        if lines || args.random || args.state.is_some() || args.step != 1 || args.descending {
+            return Err(
+                "--backend gpu cannot be combined with --stdin, --input, --random, --state, \
+                 --step or --descending"
+                    .into(),
+            );
+        }
+        if !matches!(args.algo, Algo::Sha256) {
+            return Err("--backend gpu only supports --algo sha256".into());
+        }
+    }
+    if (args.best || args.histogram) && !matches!(args.backend, Backend::Threads) {
+        return Err("--best and --histogram cannot be combined with --backend".into());
+    }
+    if args.hmac_key.is_some() && !cfg!(feature = "hmac") {
+        return Err("--hmac-key requires building with the hmac feature".into());
+    }
+    if args.metrics_addr.is_some() && !cfg!(feature = "metrics") {
+        return Err("--metrics-addr requires building with the metrics feature".into());
+    }
+    if args.exact_attempts.is_some()
+        && (lines
+            || args.random
+            || args.end.is_some()
+            || args.shard.is_some()
+            || args.timeout.is_some()
+            || args.checkpoint.is_some()
+            || args.state.is_some())
+    {
+        return Err(
+            "--exact-attempts cannot be combined with --stdin, --input, --random, \
+                    --end, --shard, --timeout, --checkpoint or --state"
+                .into(),
+        );
+    }
+    if args.show_input && matches!(args.format, Format::Binary) {
+        return Err("--show-input cannot be combined with --format binary".into());
+    }
+    if args.quiet && args.verbose {
+        return Err("--quiet conflicts with --verbose".into());
+    }
+    if args.numa && !args.pin {
+        return Err("--numa requires --pin".into());
+    }
+    if args.pin && core_affinity::get_core_ids().is_none() {
+        args.verbosity()
+            .warn("pinning threads is not supported here, --pin has no effect");
+    }
+    let encoding = match (args.big_endian, args.ascii, args.encoding) {
+        (false, false, encoding) => encoding,
+        (true, true, _) => return Err("--big-endian conflicts with --ascii".into()),
+        (true, false, Encoding::Le | Encoding::Be) => Encoding::Be,
+        (true, false, _) => return Err("--big-endian conflicts with --encoding".into()),
+        (false, true, Encoding::Le | Encoding::Decimal) => Encoding::Decimal,
+        (false, true, _) => return Err("--ascii conflicts with --encoding".into()),
+    };
+    let (encoding, width) = match (encoding, args.pad_width) {
+        (encoding, None) => (encoding, args.width),
+        (Encoding::Decimal, Some(digits)) => (Encoding::PaddedDecimal, digits),
+        _ => return Err("--pad-width requires --ascii or --encoding decimal".into()),
+    };
+    if args.double && args.hmac_key.is_some() {
+        return Err("--double cannot be combined with --hmac-key".into());
+    }
+    if args.uppercase && !matches!(args.hash_encoding, HashEncoding::Hex) {
+        return Err("--uppercase only applies to hex hashes".into());
+    }
+    if args.hex_prefix && !matches!(args.hash_encoding, HashEncoding::Hex) {
+        return Err("--hex-prefix only applies to hex hashes".into());
+    }
+    if args.message.is_some() && !args.prefix_bytes.0.is_empty() {
+        return Err("--message cannot be combined with --prefix-bytes".into());
+    }
+    if args.width == 0 || args.width > MAX_WIDTH {
+        return Err(format!("--width must be from 1 to {MAX_WIDTH}"));
+    }
+    let mut end = args.end;
+    if let Some(digits) = args.pad_width {
+        if digits == 0 || digits > MAX_ENCODED_SIZE {
+            return Err(format!("--pad-width must be from 1 to {MAX_ENCODED_SIZE}"));
+        }
+        // wider numbers would not be padded to the same length
+        if let Some(limit) = 10usize.checked_pow(digits as u32) {
+            if args.start >= limit || end.is_some_and(|end| end > limit) {
+                return Err(format!("the range does not fit in {digits} digits"));
+            }
+            // counting down never leaves the digits --start fits in
+            if !args.descending {
+                end = Some(end.unwrap_or(limit));
+            }
+        }
+    }
+    // narrow binary encodings cannot represent every number
+    let limit = 1usize.checked_shl(8 * args.width as u32);
+    if let (Encoding::Le | Encoding::Be, Some(limit)) = (encoding, limit) {
+        if args.start >= limit {
+            return Err(format!("--start does not fit in {} bytes", args.width));
+        }
+        if !args.descending {
+            end = Some(end.map_or(limit, |end| end.min(limit)));
+        }
+    }
+    if let Some(attempts) = args.exact_attempts {
+        let exact_end = attempts
+            .checked_mul(args.step)
+            .and_then(|distance| args.start.checked_add(distance));
+        if exact_end.is_none() || end.is_some_and(|end| Some(end) < exact_end) {
+            return Err("--exact-attempts runs past the largest number that fits".into());
+        }
+        end = exact_end;
+    }
+    let mut start = args.start;
+    if let Some(shard) = args.shard {
+        let (shard_start, shard_end) = shard.range(start, end.unwrap_or(usize::MAX));
+        if shard_start == shard_end {
+            return Err(format!("the range is too small for {} shards", shard.count));
+        }
+        start = shard_start;
+        end = Some(shard_end);
+    }
+    if let Some(path) = &args.checkpoint {
+        if let Some(resumed) = load_checkpoint(path)? {
+            args.verbosity()
+                .note(format_args!("resuming from {}", resumed.frontier));
+            start = resumed.frontier;
+        }
+    }
+    let checks = if args.bench.is_some() || args.histogram || args.best {
+        vec![Check::NEVER]
+    } else {
+        make_checks(args, args.algo)?
+    };
+    Ok(Config {
+        start,
+        end,
+        step: args.step,
+        descending: args.descending,
+        encoding,
+        width,
+        // the message is absorbed once and the state cloned per nonce, like any prefix
+        prefix_bytes: args
+            .message
+            .as_ref()
+            .unwrap_or(&args.prefix_bytes)
+            .0
+            .clone(),
+        double: args.double,
+        hmac_key: args.hmac_key.as_ref().map(|key| key.0.clone()),
+        channel_capacity: match (args.channel, args.channel_capacity, args.max_pending) {
+            (Some(Channel(capacity)), None, None)
+            | (None, Some(capacity), None)
+            | (None, None, Some(capacity)) => capacity,
+            (None, None, None) => DEFAULT_CHANNEL_CAPACITY,
+            _ => {
+                return Err(
+                    "--channel, --channel-capacity and --max-pending cannot be combined".into(),
+                )
+            }
+        },
+        overflow: if args.drop_oldest {
+            Overflow::DropOldest
+        } else {
+            Overflow::Wait
+        },
+        pin: args.pin,
+        numa: args.numa,
+        seed: args
+            .random
+            .then(|| args.seed.unwrap_or_else(|| random_seed(args.verbosity()))),
+        batch_size: args
+            .batch
+            .unwrap_or_else(|| adaptive_batch_size(&checks, args.algo.digest_size())),
+        checks,
+        // benchmarks never hash them, as nothing passes the first algorithm
+        also: if args.bench.is_some() || args.histogram {
+            Vec::new()
+        } else {
+            args.also()
+                .iter()
+                .map(|&algo| Ok((algo, make_checks(args, algo)?)))
+                .collect::<Result<_, String>>()?
+        },
+        ..Config::new(Check::EMPTY, args.workers.unwrap_or_else(num_cpus::get))
+    })
+}
+
+/// `-N` targets, strongest first
+fn targets(args: &Args) -> Vec<usize> {
+    let mut targets = args.num_zeros.clone();
+    targets.sort_unstable_by(|a, b| b.cmp(a));
+    targets.dedup();
+    targets
+}
+
+/// Builds a check for every `-N` target, in the order of [`targets`]
+fn make_checks(args: &Args, algo: Algo) -> Result<Vec<Check>, String> {
+    let digest_size = algo.digest_size();
+    // requirements on the whole digest, each excluding all others
+    let whole = match (&args.target, &args.max_hash) {
+        (Some(_), Some(_)) => return Err("--target cannot be combined with --max-hash".into()),
+        (Some(target), None) => Some(("--target", target, Check::exact(&target.0))),
+        (None, Some(max)) => Some(("--max-hash", max, Check::at_most(&max.0))),
+        (None, None) => None,
+    };
+    if let Some((name, digest, check)) = whole {
+        if !args.num_zeros.is_empty()
+            || args.zero_bits.is_some()
+            || args.prefix.is_some()
+            || args.suffix.is_some()
+        {
+            return Err(format!(
+                "{name} cannot be combined with -N, --zero-bits, --prefix or --suffix"
+            ));
+        }
+        if digest.0.len() != digest_size {
+            return Err(format!(
+                "{name} has {} bytes, {algo} digests have {digest_size}",
+                digest.0.len(),
+            ));
+        }
+        return Ok(vec![check]);
+    }
+    if args.num_zeros.is_empty() {
+        return Ok(vec![make_check(args, None, algo)?]);
+    }
+    targets(args)
+        .into_iter()
+        .map(|num_zeros| make_check(args, Some(num_zeros), algo))
+        .collect()
+}
+
+/// Combines a zero count and hex pattern options into a single check
+///
+/// `-N` or `--zero-bits` constrains the end of the hash selected by `--leading`
+/// and cannot be combined with a pattern for the same end.
+/// Prefix and suffix are checked together and must fit the digest without overlapping.
+fn make_check(args: &Args, num_zeros: Option<usize>, algo: Algo) -> Result<Check, String> {
+    let digest_size = algo.digest_size();
+    let direction = args.direction();
+    // zero requirement along with the number of nibbles it touches
+    let zeros = match (num_zeros, args.zero_bits) {
+        (Some(_), Some(_)) => return Err("-N and --zero-bits are mutually exclusive".into()),
+        (Some(0), None) | (None, Some(0)) => {
+            return Err("zero count must be at least 1, otherwise every number matches".into())
+        }
+        (Some(num_zeros), None) if num_zeros > digest_size * 2 => {
+            return Err(format!(
+                "num_zeros {num_zeros} exceeds {} nibbles for {algo}",
+                digest_size * 2,
+            ))
+        }
+        (None, Some(bits)) if bits > digest_size * 8 => {
+            return Err(format!(
+                "zero_bits {bits} exceeds {} bits for {algo}",
+                digest_size * 8,
+            ))
+        }
+        (Some(num_zeros), None) => {
+            Some((num_zeros, Check::zeros(num_zeros, digest_size, direction)))
+        }
+        (None, Some(bits)) => Some((
+            bits.div_ceil(4),
+            Check::zero_bits(bits, digest_size, direction),
+        )),
+        (None, None) => None,
+    };
+    let zeros = match (&args.zero_value, zeros) {
+        (None, zeros) => zeros,
+        (Some(_), None) => return Err("--zero-value requires -N or --zero-bits".into()),
+        (Some(value), Some((nibbles, check))) => match value.0[..] {
+            [nibble] => Some((nibbles, check.repeating(nibble))),
+            _ => return Err(format!("--zero-value `{value}` must be a single hex digit")),
+        },
+    };
+    let pattern = |pattern: &HexPattern, direction| {
+        (
+            pattern.0.len(),
+            Check::pattern(pattern, digest_size, direction),
+        )
+    };
+    let prefix = args.prefix.as_ref().map(|p| pattern(p, Direction::Leading));
+    let suffix = args
+        .suffix
+        .as_ref()
+        .map(|p| pattern(p, Direction::Trailing));
+
+    let (start, end) = match (direction, zeros) {
+        (_, None) => (prefix, suffix),
+        (Direction::Leading, Some(_)) if prefix.is_some() => {
+            return Err("leading zeroes and --prefix both constrain the start of the hash".into())
+        }
+        (Direction::Trailing, Some(_)) if suffix.is_some() => {
+            return Err(
+                "trailing zeroes and --suffix both constrain the end of the hash, \
+                 pass --leading to search for leading zeroes"
+                    .into(),
+            )
+        }
+        (Direction::Leading, zeros) => (zeros, suffix),
+        (Direction::Trailing, zeros) => (prefix, zeros),
+    };
+    if start.is_none() && end.is_none() {
+        return Err(
+            "either -N, --zero-bits, --prefix, --suffix, --target or --max-hash is required".into(),
+        );
+    }
+
+    let nibbles = digest_size * 2;
+    let checked = start.iter().chain(&end).map(|(n, _)| n).sum::<usize>();
+    if checked > nibbles {
+        return Err(format!(
+            "{checked} checked nibbles exceed {nibbles} nibbles for {algo}"
+        ));
+    }
+
+    let mut check = Check::EMPTY;
+    for (_, part) in start.iter().chain(&end) {
+        check.merge(part);
+    }
+    Ok(check)
 }
 
-fn search(num_zeros: usize, mut count: usize, workers: usize) {
-    let result_rx = spawn_workers(num_zeros, workers);
-    while count > 0 {
-        let (n, hash) = result_rx
-            .recv()
-            .expect("Catastrophic failure, all worker threads are dead");
+/// Thread reading lines from stdin or the input file
+type Reader = JoinHandle<Result<(), SearchError>>;
 
-        println!("{n}: {hash}");
-        count -= 1;
+/// Starts the workers selected by the arguments,
+/// along with the line reader if lines are hashed
+fn spawn(args: &Args, config: Config) -> Result<(Workers, Option<Reader>), SearchError> {
+    if let Some(path) = &args.input {
+        let file = File::open(path).map_err(|e| {
+            let context = format!("failed to open input file {}: {e}", path.display());
+            SearchError::Io(io::Error::new(e.kind(), context))
+        })?;
+        // lines are streamed, so the file can be far larger than memory
+        let (lines, reader) = spawn_line_reader(io::BufReader::new(file));
+        Ok((args.algo.spawn_line_workers(config, lines)?, Some(reader)))
+    } else if args.stdin {
+        let (lines, reader) = spawn_line_reader(io::BufReader::new(io::stdin()));
+        Ok((args.algo.spawn_line_workers(config, lines)?, Some(reader)))
+    } else if args.best {
+        let workers = args.algo.spawn_best_workers(config, args.direction())?;
+        Ok((workers, None))
+    } else {
+        match args.backend {
+            Backend::Threads => Ok((args.algo.spawn_workers(config)?, None)),
+            #[cfg(feature = "rayon")]
+            Backend::Rayon => {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(config.workers)
+                    .build()
+                    .map_err(|e| {
+                        SearchError::Io(io::Error::other(format!(
+                            "failed to start the rayon thread pool: {e}"
+                        )))
+                    })?;
+                Ok((args.algo.spawn_pool_workers(config, Arc::new(pool))?, None))
+            }
+            #[cfg(feature = "opencl")]
+            Backend::Gpu => Ok((hashsearch::spawn_gpu_workers(config)?, None)),
+        }
     }
 }
 
-fn spawn_workers(num_zeros: usize, workers: usize) -> Receiver<(usize, String)> {
-    const BATCH_SIZE: usize = 100;
-    let mask = make_check_mask(num_zeros);
+/// Hashes without ever matching for `duration` and prints the hash rate
+fn bench(args: &Args, config: Config, duration: Duration) -> Result<(), SearchError> {
+    let threads = config.workers;
+    let (hashes, elapsed) = measure(args, config, duration)?;
+    let rate = hashes as f64 / elapsed;
+    println!(
+        "{}: {hashes} hashes in {elapsed:.2} s, {}H/s, {}H/s per thread",
+        args.algo,
+        si_prefixed(rate),
+        si_prefixed(rate / threads as f64)
+    );
+    Ok(())
+}
 
-    let (result_tx, result_rx) = unbounded();
+/// Runs the search for `duration`, returning the number of hashes and seconds taken
+fn measure(args: &Args, config: Config, duration: Duration) -> Result<(u64, f64), SearchError> {
+    let (workers, _) = spawn(args, config)?;
+    let progress = workers.progress();
+    let started = Instant::now();
+    thread::sleep(duration);
+    let hashes = progress.hashes();
+    let elapsed = started.elapsed().as_secs_f64();
+    drop(workers);
+    if args.verbosity() >= Verbosity::Verbose {
+        print_worker_stats(&progress);
+    }
+    Ok((hashes, elapsed))
+}
 
-    for i in 0..workers {
-        let th_result_tx = result_tx.clone();
+/// Prints the hash of `n` and whether it passes the check, failing if it does not
+fn verify(args: &Args, config: &Config, n: usize) -> Result<(), String> {
+    if let Some(source) = args.line_source() {
+        return Err(format!(
+            "--verify hashes an integer, not lines from {source}"
+        ));
+    }
+    let (digest, check) = args.algo.verify(config, n).map_err(|e| e.to_string())?;
+    println!("{n}: {}", HashText::new(&digest, args));
+    match check {
+        Some(_) => {
+            println!("{n} passes the check");
+            Ok(())
+        }
+        None => Err(format!("{n} does not pass the check")),
+    }
+}
 
+/// Prints how many hashes of the range had each number of zero nibbles,
+/// along with the share expected of uniformly distributed digests
+fn histogram(args: &Args, config: Config) -> Result<(), String> {
+    let direction = args.direction();
+    let cancel = CancelToken::new();
+    if let Some(seconds) = args.timeout {
+        let cancel = cancel.clone();
         thread::spawn(move || {
-            let mut results_buf = Vec::with_capacity(BATCH_SIZE);
-            let mut start = BATCH_SIZE * i + 1;
-            let mut hasher = Sha256::new();
-            let mut hash = GenericArray::default();
-            loop {
-                for n in start..start + BATCH_SIZE {
-                    hasher.update(n.to_le_bytes());
-                    hasher.finalize_into_reset(&mut hash);
-
-                    // SAFETY:
-                    // GenericArray<T, S> wraps [T; S] and
-                    // it is generally safe to transmute arrays of matching byte size
-                    // Reasoning:
-                    // after profiling with `perf` and `flamegraph`
-                    // this approach proved to decrease CPU time spent
-                    // outside `Sha256::finalize` by about 20%
-                    // when compared to naive byte-wise iterator
-                    let valid = unsafe {
-                        let hash = transmute::<_, [u64; 4]>(hash);
-                        hash.into_iter().zip(mask).all(|(hb, mb)| hb & mb == 0)
-                    };
+            thread::sleep(Duration::from_secs(seconds));
+            cancel.cancel();
+        });
+    }
+    let config = Config {
+        cancel: Some(cancel),
+        ..config
+    };
+    let counts = args
+        .algo
+        .zero_histogram(&config, direction)
+        .map_err(|e| e.to_string())?;
+    let total = counts.iter().sum::<u64>().max(1) as f64;
+    let last = counts.iter().rposition(|&count| count > 0).unwrap_or(0);
+    for (zeros, count) in counts.iter().enumerate().take(last + 1) {
+        // exactly `zeros` zero nibbles followed by a nonzero one, unless all are zero
+        let expected = if zeros == counts.len() - 1 {
+            16f64.powi(-(zeros as i32))
+        } else {
+            16f64.powi(-(zeros as i32)) * 15.0 / 16.0
+        };
+        println!(
+            "{zeros}: {count} ({:.4}%, expected {:.4}%)",
+            *count as f64 * 100.0 / total,
+            expected * 100.0
+        );
+    }
+    Ok(())
+}
 
-                    if valid {
-                        results_buf.push((n, format!("{hash:x}")))
-                    }
-                }
+/// Number of hashes it takes on average to find `--count` results of every check,
+/// or a single one if the count is unlimited
+fn expected_hashes(args: &Args, config: &Config) -> f64 {
+    let digest_size = args.algo.digest_size();
+    config
+        .checks
+        .iter()
+        .enumerate()
+        .map(|(i, check)| {
+            // digests of different algorithms are independent
+            let also = config
+                .also
+                .iter()
+                .map(|(algo, checks)| checks[i].probability(algo.digest_size()));
+            let probability = check.probability(digest_size) * also.product::<f64>();
+            args.count.max(1) as f64 / probability
+        })
+        .fold(0.0, f64::max)
+}
 
-                for (n, hash) in results_buf.drain(..) {
-                    if th_result_tx.send((n, hash)).is_err() {
-                        return;
-                    }
+/// Prints what a search with `config` would do
+///
+/// Fails like the search would if `config` does not [validate](Config::validate).
+fn dry_run(args: &Args, config: &Config) -> Result<(), SearchError> {
+    config.validate()?;
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    println!("algo: {}", args.algo);
+    match args.line_source() {
+        Some(source) => println!("candidates: lines of {source}"),
+        None => {
+            let end = config.end.map_or_else(
+                || if config.descending { "0" } else { "unbounded" }.into(),
+                |end| end.to_string(),
+            );
+            println!("range: {} to {end}", config.start);
+            if config.step != 1 || config.descending {
+                let sign = if config.descending { "-" } else { "" };
+                println!("step: {sign}{}", config.step);
+            }
+            println!("encoding: {}, width {}", config.encoding, config.width);
+        }
+    }
+    if let Some(seed) = config.seed {
+        println!("random with seed {seed}");
+    }
+    if !config.prefix_bytes.is_empty() {
+        println!("prefix bytes: {}", hex(&config.prefix_bytes));
+    }
+    if config.double {
+        println!("double hashing");
+    }
+    if let Some(key) = &config.hmac_key {
+        println!("hmac key: {}", hex(key));
+    }
+    let channel = match (config.channel_capacity, config.overflow) {
+        (UNBOUNDED_CHANNEL, _) => "unbounded".into(),
+        (capacity, Overflow::Wait) => capacity.to_string(),
+        (capacity, Overflow::DropOldest) => format!("{capacity}, dropping the oldest"),
+    };
+    println!(
+        "workers: {}, batch: {}, channel: {channel}",
+        config.workers, config.batch_size
+    );
+    let algos = iter::once((args.algo, &config.checks))
+        .chain(config.also.iter().map(|(algo, checks)| (*algo, checks)));
+    for (algo, checks) in algos {
+        let digest_size = algo.digest_size();
+        for (i, check) in checks.iter().enumerate() {
+            println!("{algo} check {i}:");
+            println!("  mask     {}", hex(&check.mask()[..digest_size]));
+            println!("  expected {}", hex(&check.expected()[..digest_size]));
+            if let Some(max) = check.max() {
+                println!("  at most  {}", hex(&max[..digest_size]));
+            }
+        }
+    }
+    println!(
+        "expected hashes: {}",
+        approximate(expected_hashes(args, config))
+    );
+    Ok(())
+}
+
+/// Prints the expected number of hashes and, after a calibration run, how long they take
+fn estimate(args: &Args, config: Config) -> Result<(), SearchError> {
+    const CALIBRATION: Duration = Duration::from_secs(1);
+    let expected = expected_hashes(args, &config);
+    let calibration = Config {
+        checks: vec![Check::NEVER],
+        ..config
+    };
+    let (hashes, elapsed) = measure(args, calibration, CALIBRATION)?;
+    let rate = hashes as f64 / elapsed;
+    println!(
+        "expected {} hashes, {} at {}H/s",
+        approximate(expected),
+        human_duration(expected / rate),
+        si_prefixed(rate)
+    );
+    Ok(())
+}
+
+/// Drains results from the workers and prints `count` of them to stdout,
+/// or as many as there are in a finite range or the input, or all of them if `count` is 0
+///
+/// Returns the exit code, 2 if the timeout passed before `count` results were found,
+/// 130 if interrupted by Ctrl-C and 1 if writing results failed.
+///
+/// The CSV header is left out if `out` continues the output of a resumed search.
+fn print_results(args: &Args, mut config: Config, mut out: impl Write, continued: bool) -> i32 {
+    let started = Instant::now();
+    let verbosity = args.verbosity();
+    let expected = expected_hashes(args, &config);
+    let max_pending = config.channel_capacity;
+    let state = match args
+        .state
+        .as_deref()
+        .map(|path| StateFile::open(path, &config, args.batch))
+    {
+        Some(Ok((state, resume))) => {
+            if let Some(frontier) = resume.as_ref().and_then(|resume| resume.iter().min()) {
+                verbosity.note(format_args!(
+                    "resuming, everything below {frontier} was scanned"
+                ));
+            }
+            config.resume = resume;
+            Some(state)
+        }
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            return 1;
+        }
+        None => None,
+    };
+    let (workers, reader) = match spawn(args, config) {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            eprintln!("{e}");
+            return 1;
+        }
+    };
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = interrupted.clone();
+        let stop = workers.stop_flag();
+        let handler = move || {
+            if interrupted.swap(true, Ordering::Relaxed) {
+                verbosity.note("interrupted twice, exiting immediately");
+                process::exit(130);
+            }
+            // results found so far are still drained and printed once the workers exit
+            stop.store(true, Ordering::Relaxed);
+        };
+        if let Err(e) = ctrlc::set_handler(handler) {
+            verbosity.warn(format_args!("failed to install the Ctrl-C handler: {e}"));
+        }
+    }
+    let found_count = Arc::new(AtomicUsize::new(0));
+    #[cfg(feature = "metrics")]
+    if let Some(addr) = args.metrics_addr {
+        let served = spawn_metrics_server(addr, workers.progress(), found_count.clone(), started);
+        if let Err(e) = served {
+            eprintln!("{e}");
+            return 1;
+        }
+    }
+    let (done_tx, done_rx) = bounded::<()>(0);
+    let display = (args.progress && verbosity > Verbosity::Quiet).then(|| {
+        // a bar needs an expected number of hashes to fill up towards
+        if io::stderr().is_terminal() && expected.is_finite() {
+            Display::Bar(progress_bar(expected))
+        } else {
+            Display::Lines
+        }
+    });
+    // output is written with the bar hidden, which is then redrawn below it
+    let bar = match &display {
+        Some(Display::Bar(bar)) => Some(bar.clone()),
+        _ => None,
+    };
+    // results past the frontier printed before, which the resumed search finds again,
+    // and those printed since, saved along with the frontier
+    let mut skip = BTreeSet::new();
+    let checkpoint = match args.checkpoint.as_deref().map(load_checkpoint) {
+        Some(Ok(resumed)) => {
+            skip = resumed.map(|resumed| resumed.printed).unwrap_or_default();
+            args.checkpoint
+                .clone()
+                .map(|path| (path, Arc::new(Mutex::new(skip.clone()))))
+        }
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            return 1;
+        }
+        None => None,
+    };
+    let reporter = (display.is_some() || checkpoint.is_some() || state.is_some()).then(|| {
+        spawn_reporter(
+            workers.progress(),
+            found_count.clone(),
+            done_rx,
+            display,
+            checkpoint.clone(),
+            state,
+        )
+    });
+
+    let targets = targets(args);
+    let mut code = 0;
+    if let (Format::Csv, None, false) = (args.format, &args.format_template, continued) {
+        let mut header = vec!["n".to_string()];
+        header.extend(args.line_source().map(|_| "line".into()));
+        header.push("hash".into());
+        header.extend(args.also().iter().map(Algo::to_string));
+        header.extend((targets.len() > 1 || args.best).then(|| "zeros".into()));
+        header.extend(args.attempts.then(|| "attempts".into()));
+        header.extend(args.show_input.then(|| "input".into()));
+        if let Err(e) = write_csv_row(&mut out, &header) {
+            eprintln!("failed to write results: {e}");
+            code = 1;
+        }
+    }
+    // a timeout too far out to represent never passes
+    let deadline = args
+        .timeout
+        .and_then(|timeout| Instant::now().checked_add(Duration::from_secs(timeout)));
+    let mut sorted = args.sorted.then(|| SortedResults::new(&workers));
+    // results still wanted for each check, a count of 0 never runs out
+    let limit =
+        (args.count > 0 && !args.best && args.exact_attempts.is_none()).then_some(args.count);
+    let mut remaining = vec![limit.unwrap_or(usize::MAX); targets.len().max(1)];
+    let total = limit.map(|count| count * remaining.len());
+    let of_total = |found| match total {
+        Some(total) => format!("{found} of {total}"),
+        None => format!("{found}"),
+    };
+    let mut printed = 0;
+    let mut best = 0;
+    let mut line = Vec::new();
+    while total.is_none_or(|total| printed < total) && code == 0 {
+        let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        let received = match (&mut sorted, deadline) {
+            // results arriving faster than they are printed would otherwise outlast the deadline
+            _ if timed_out => Err(RecvTimeoutError::Timeout),
+            (Some(sorted), Some(deadline)) => sorted.recv_deadline(deadline),
+            (Some(sorted), None) => sorted.recv().map_err(RecvTimeoutError::from),
+            (None, Some(deadline)) => workers.results().recv_deadline(deadline),
+            (None, None) => workers.results().recv().map_err(RecvTimeoutError::from),
+        };
+        if let Err(RecvTimeoutError::Timeout) = received {
+            without_bar(bar.as_ref(), || {
+                verbosity.note(format_args!(
+                    "timed out, found {} results",
+                    of_total(printed)
+                ))
+            });
+            // without a count, the timeout is how the search is meant to end
+            code = if total.is_some() { 2 } else { 0 };
+            break;
+        }
+        let Ok(found) = received else {
+            if interrupted.load(Ordering::Relaxed) {
+                let scanned = if args.scans_below_frontier() {
+                    format!(
+                        ", scanned everything below {}",
+                        workers.progress().frontier()
+                    )
+                } else {
+                    String::new()
+                };
+                without_bar(bar.as_ref(), || {
+                    verbosity.note(format_args!(
+                        "interrupted, found {} results{scanned}",
+                        of_total(printed)
+                    ))
+                });
+                code = 130;
+                break;
+            }
+            if let Some(reader) = reader {
+                // workers only exit on their own once the reader is done
+                let result = reader.join().expect("line reader panicked");
+                if let Err(e) = result {
+                    without_bar(bar.as_ref(), || eprintln!("failed to read input: {e}"));
+                    // results found before are still flushed and summarized
+                    code = 1;
+                    break;
                 }
+                without_bar(bar.as_ref(), || {
+                    verbosity.note(format_args!(
+                        "input exhausted, found {} results",
+                        of_total(printed)
+                    ))
+                });
+                break;
+            }
+            if !workers.progress().exhausted() {
+                without_bar(bar.as_ref(), || {
+                    eprintln!("{}", SearchError::AllWorkersDead)
+                });
+                code = 1;
+                break;
+            }
+            // random draws can miss a candidate, however unlikely
+            let exhausted = if args.random {
+                "range likely exhausted"
+            } else {
+                "range exhausted"
+            };
+            without_bar(bar.as_ref(), || {
+                verbosity.note(format_args!(
+                    "{exhausted}, found {} results",
+                    of_total(printed)
+                ))
+            });
+            break;
+        };
 
-                start += BATCH_SIZE * workers;
+        if let (true, Some((_, printed))) = (skip.remove(&found.n), &checkpoint) {
+            // the frontier may have passed it meanwhile, but not where the search stops
+            printed.lock().expect("printed results").insert(found.n);
+            continue;
+        }
+        // targets are nested, so a result passing a satisfied one passes all weaker ones
+        let Some(check) = (found.check..remaining.len()).find(|&check| remaining[check] > 0) else {
+            continue;
+        };
+        remaining[check] -= 1;
+        let zeros = if args.best {
+            let zeros = found.hash.zero_nibbles(args.direction());
+            // a record beaten by one received earlier is no record
+            if zeros <= best {
+                continue;
+            }
+            best = zeros;
+            Some(zeros)
+        } else {
+            (targets.len() > 1).then(|| targets[check])
+        };
+        found_count.fetch_add(1, Ordering::Relaxed);
+        printed += 1;
+        let written = if printed.is_multiple_of(args.sample) {
+            // a record is written in one piece, so a full buffer never flushes half of it
+            line.clear();
+            write_result(&mut line, args, &found, zeros).and_then(|()| out.write_all(&line))
+        } else {
+            Ok(())
+        };
+        // many results arriving at once are written together, a lone one right away
+        let written = written.and_then(|()| {
+            if workers.results().is_empty() {
+                without_bar(bar.as_ref(), || out.flush())?;
             }
+            Ok(())
         });
+        if let Err(e) = written {
+            eprintln!("failed to write results: {e}");
+            code = 1;
+            break;
+        }
+        if let Some((_, printed)) = &checkpoint {
+            printed.lock().expect("printed results").insert(found.n);
+        }
+    }
+    // a failed write was already reported
+    if let (Err(e), 0 | 2 | 130) = (without_bar(bar.as_ref(), || out.flush()), code) {
+        eprintln!("failed to write results: {e}");
+        code = 1;
+    }
+    let held_back = sorted.and_then(|sorted| sorted.pending().next().map(|found| found.n));
+    let progress = workers.progress();
+    let unprinted = workers.stop();
+    drop(done_tx);
+    let state = reporter.and_then(|reporter| reporter.join().expect("progress reporter panicked"));
+    if verbosity >= Verbosity::Verbose {
+        print_worker_stats(&progress);
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+    verbosity.note(format_args!(
+        "hashed {} candidates, found {printed} results in {elapsed:.2} s, {}H/s",
+        progress.hashes(),
+        si_prefixed(progress.hashes() as f64 / elapsed)
+    ));
+    if args.drop_oldest {
+        verbosity.note(format_args!(
+            "at most {max_pending} results were pending, {} of the oldest dropped",
+            progress.dropped()
+        ));
+    } else if args.max_pending.is_some() {
+        verbosity.note(format_args!(
+            "at most {max_pending} results were pending, workers paused while full"
+        ));
     }
-    result_rx
+    if let Some(mut state) = state {
+        // the batches of results that were found but not printed are hashed again on resume
+        let mut frontiers = progress.worker_frontiers();
+        for n in unprinted.iter().map(|found| found.n).chain(held_back) {
+            state.rewind(&mut frontiers, n);
+        }
+        state.save(&frontiers);
+    }
+    if let Some((path, printed)) = &checkpoint {
+        // those not found again yet are either past the frontier or about to be hashed again
+        printed.lock().expect("printed results").append(&mut skip);
+        // results that were found but not printed have to be found again on resume
+        let frontier = unprinted.iter().map(|found| found.n).chain(held_back).min();
+        save_checkpoint(
+            path,
+            progress.frontier().min(frontier.unwrap_or(usize::MAX)),
+            printed,
+        );
+    }
+    code
+}
+
+/// Writes a single result in the selected format,
+/// along with the `-N` target it counts towards if there are several
+fn write_result(
+    out: &mut impl Write,
+    args: &Args,
+    found: &Found,
+    zeros: Option<usize>,
+) -> io::Result<()> {
+    let hash = HashText::new(&found.hash, args);
+    let also = found.also.iter().map(|digest| HashText::new(digest, args));
+    if let Some(template) = &args.format_template {
+        return template.write(out, args, found, zeros);
+    }
+    match args.format {
+        Format::Plain => {
+            match &found.line {
+                Some(line) => write!(out, "{line}: {hash}")?,
+                None => write!(out, "{}: {hash}", found.n)?,
+            }
+            for hash in also {
+                write!(out, " {hash}")?;
+            }
+            if let Some(zeros) = zeros {
+                write!(out, " ({zeros} zeroes)")?;
+            }
+            if args.attempts {
+                write!(out, " (found after ~{} attempts)", found.attempts)?;
+            }
+            if args.show_input {
+                write!(out, " (input {})", found.input)?;
+            }
+            writeln!(out)
+        }
+        Format::Json => {
+            let record = Record::new(
+                found,
+                args,
+                zeros,
+                |digest| HashText::new(digest, args),
+                |input| input,
+            );
+            serde_json::to_writer(&mut *out, &record)?;
+            writeln!(out)
+        }
+        Format::Csv => {
+            let mut fields = vec![found.n.to_string()];
+            fields.extend(found.line.clone());
+            fields.push(hash.to_string());
+            fields.extend(also.map(|hash| hash.to_string()));
+            fields.extend(zeros.map(|zeros| zeros.to_string()));
+            fields.extend(args.attempts.then(|| found.attempts.to_string()));
+            fields.extend(args.show_input.then(|| found.input.to_string()));
+            write_csv_row(out, &fields)
+        }
+        Format::Binary => {
+            out.write_all(&(found.n as u64).to_le_bytes())?;
+            out.write_all(found.hash.as_bytes())?;
+            found
+                .also
+                .iter()
+                .try_for_each(|digest| out.write_all(digest.as_bytes()))
+        }
+        Format::Msgpack => {
+            let record = Record::new(
+                found,
+                args,
+                zeros,
+                |digest| RawBytes(digest.as_bytes()),
+                |input| RawBytes(&input.0),
+            );
+            let packed = rmp_serde::to_vec_named(&record).map_err(io::Error::other)?;
+            out.write_all(&(packed.len() as u32).to_le_bytes())?;
+            out.write_all(&packed)
+        }
+    }
+}
+
+/// How --progress is shown on stderr
+enum Display {
+    /// a line every second, for logs and pipes
+    Lines,
+    /// a bar updated in place towards the expected number of hashes, on a terminal
+    Bar(ProgressBar),
+}
+
+/// Bar with the share of `expected` hashes done, the elapsed time and the hash rate
+fn progress_bar(expected: f64) -> ProgressBar {
+    let bar = ProgressBar::new(expected as u64);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{elapsed_precise} [{wide_bar}] {percent}% of expected, {msg}",
+        )
+        .expect("the template is valid")
+        .progress_chars("=> "),
+    );
+    bar
 }
 
-/// Produces an array where all bits
-/// except for last `num_zeros` nibbles are set to 0
-fn make_check_mask(num_zeros: usize) -> [u64; 4] {
-    let bytes_to_check = num_zeros / 2 + num_zeros % 2;
-    let extra_nibble = num_zeros % 2 != 0;
-    let bytes = std::array::from_fn(|i| match 32 - i {
-        ri if ri == bytes_to_check && extra_nibble => 0x0f,
-        ri if ri <= bytes_to_check => 0xff,
-        _ => 0x00,
+/// Runs `f` with the progress bar hidden, if there is one, so it does not draw over the output
+fn without_bar<T>(bar: Option<&ProgressBar>, f: impl FnOnce() -> T) -> T {
+    match bar {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
+
+/// Spawns a thread that every second shows the hash rate on stderr
+/// and saves the checkpoint and state file, until `done` is disconnected,
+/// then returns the state file
+///
+/// Results still in the channel when the process is killed
+/// are lost to a search resumed from such a checkpoint.
+fn spawn_reporter(
+    progress: Arc<Progress>,
+    found: Arc<AtomicUsize>,
+    done: Receiver<()>,
+    display: Option<Display>,
+    checkpoint: Option<(PathBuf, Arc<Mutex<BTreeSet<usize>>>)>,
+    mut state: Option<StateFile>,
+) -> JoinHandle<Option<StateFile>> {
+    const INTERVAL: Duration = Duration::from_secs(1);
+    thread::spawn(move || {
+        let mut last_hashes = 0;
+        let mut last_time = Instant::now();
+        while let Err(RecvTimeoutError::Timeout) = done.recv_timeout(INTERVAL) {
+            if let Some(display) = &display {
+                let hashes = progress.hashes();
+                let now = Instant::now();
+                let rate = (hashes - last_hashes) as f64 / (now - last_time).as_secs_f64();
+                let found = found.load(Ordering::Relaxed);
+                match display {
+                    Display::Lines => {
+                        // written at once, as results may go to the same terminal meanwhile
+                        let line = format!(
+                            "{}H/s, {:.1e} hashes, {found} found\n",
+                            si_prefixed(rate),
+                            hashes as f64,
+                        );
+                        let _ = io::stderr().write_all(line.as_bytes());
+                    }
+                    Display::Bar(bar) => {
+                        bar.set_position(hashes);
+                        bar.set_message(format!("{}H/s, {found} found", si_prefixed(rate)));
+                    }
+                }
+                last_hashes = hashes;
+                last_time = now;
+            }
+            if let Some((path, printed)) = &checkpoint {
+                save_checkpoint(path, progress.frontier(), printed);
+            }
+            if let Some(state) = &mut state {
+                state.save(&progress.worker_frontiers());
+            }
+        }
+        if let Some(Display::Bar(bar)) = &display {
+            // the final summary takes its place
+            bar.finish_and_clear();
+        }
+        state
+    })
+}
+
+/// Spawns a thread answering every request on `addr` with the metrics of the search,
+/// until the process exits
+#[cfg(feature = "metrics")]
+fn spawn_metrics_server(
+    addr: SocketAddr,
+    progress: Arc<Progress>,
+    found: Arc<AtomicUsize>,
+    started: Instant,
+) -> Result<(), String> {
+    let server = tiny_http::Server::http(addr)
+        .map_err(|e| format!("failed to serve metrics on {addr}: {e}"))?;
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+        .expect("the header is valid");
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let body = metrics(&progress, found.load(Ordering::Relaxed), started.elapsed());
+            let response = tiny_http::Response::from_string(body).with_header(content_type.clone());
+            // a scraper hanging up early is its own business
+            let _ = request.respond(response);
+        }
     });
-    // SAFETY: it is generally safe to transmute arrays of matching byte size
-    unsafe { transmute::<[u8; 32], [u64; 4]>(bytes) }
+    Ok(())
+}
+
+/// Counters of the search in the Prometheus text format,
+/// rates are left to the scraper, e.g. `rate(hashsearch_worker_hashes_total[1m])`
+#[cfg(feature = "metrics")]
+fn metrics(progress: &Progress, found: usize, uptime: Duration) -> String {
+    let mut text = format!(
+        "# HELP hashsearch_hashes_total Candidates hashed.\n\
+         # TYPE hashsearch_hashes_total counter\n\
+         hashsearch_hashes_total {}\n\
+         # HELP hashsearch_found_total Results found.\n\
+         # TYPE hashsearch_found_total counter\n\
+         hashsearch_found_total {found}\n\
+         # HELP hashsearch_uptime_seconds Time since the search started.\n\
+         # TYPE hashsearch_uptime_seconds gauge\n\
+         hashsearch_uptime_seconds {:.3}\n\
+         # HELP hashsearch_worker_hashes_total Candidates hashed by each worker thread.\n\
+         # TYPE hashsearch_worker_hashes_total counter\n",
+        progress.hashes(),
+        uptime.as_secs_f64()
+    );
+    for (i, stats) in progress.worker_stats().iter().enumerate() {
+        text += &format!(
+            "hashsearch_worker_hashes_total{{worker=\"{i}\"}} {}\n",
+            stats.hashes
+        );
+    }
+    text
+}
+
+/// Prints how many candidates each worker thread hashed and found to stderr
+fn print_worker_stats(progress: &Progress) {
+    let stats = progress.worker_stats();
+    let total = stats.iter().map(|stats| stats.hashes).sum::<u64>().max(1);
+    for (i, stats) in stats.iter().enumerate() {
+        eprintln!(
+            "worker {i}: {} hashes ({:.1}%), {} found",
+            stats.hashes,
+            stats.hashes as f64 * 100.0 / total as f64,
+            stats.found
+        );
+    }
+}
+
+/// Where every worker continues from, memory-mapped so saving it takes a few stores
+///
+/// The file holds little-endian words: the magic `HSST` followed by the u32 version 1,
+/// then the u64 start, batch size and number of workers of the search,
+/// then the u64 candidate each worker continues from.
+struct StateFile {
+    map: MmapMut,
+    start: usize,
+    batch_size: usize,
+}
+
+impl StateFile {
+    const MAGIC: &[u8; 4] = b"HSST";
+    const VERSION: u32 = 1;
+    /// words before the ones of the workers
+    const HEADER_WORDS: usize = 4;
+
+    /// Maps the state file at `path` for a search with `config`, creating it if it is missing,
+    /// along with where the workers continue from if it existed
+    ///
+    /// `batch` is the batch size given with --batch, if one was.
+    /// Without one the saved batch size is the one adapted to the difficulty,
+    /// which the error for a mismatch points out.
+    fn open(
+        path: &Path,
+        config: &Config,
+        batch: Option<usize>,
+    ) -> Result<(Self, Option<Vec<usize>>), String> {
+        let context = |e: io::Error| format!("failed to open state file {}: {e}", path.display());
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(context)?;
+        let existed = file.metadata().map_err(context)?.len() > 0;
+        let len = (Self::HEADER_WORDS + config.workers) * 8;
+        if !existed {
+            file.set_len(len as u64).map_err(context)?;
+        }
+        // SAFETY: nothing but this search is expected to modify the file while it runs
+        let map = unsafe { MmapMut::map_mut(&file) }.map_err(context)?;
+        let mut state = Self {
+            map,
+            start: config.start,
+            batch_size: config.batch_size,
+        };
+        let header = [config.start, config.batch_size, config.workers];
+        if !existed {
+            state.map[..4].copy_from_slice(Self::MAGIC);
+            state.map[4..8].copy_from_slice(&Self::VERSION.to_le_bytes());
+            for (i, &word) in header.iter().enumerate() {
+                state.set(i + 1, word);
+            }
+            return Ok((state, None));
+        }
+
+        if state.map.len() < Self::HEADER_WORDS * 8
+            || &state.map[..4] != Self::MAGIC
+            || state.map[4..8] != Self::VERSION.to_le_bytes()
+        {
+            return Err(format!("{} is not a state file", path.display()));
+        }
+        let saved = [state.get(1), state.get(2), state.get(3)];
+        if saved != header {
+            let mut message = format!(
+                "state file {} is for --start {} --batch {} -W {}",
+                path.display(),
+                saved[0],
+                saved[1],
+                saved[2]
+            );
+            if batch.is_none() && saved[1] != config.batch_size {
+                message += &format!(
+                    ", without --batch the batch adapts to the difficulty and is {} now",
+                    config.batch_size
+                );
+            }
+            return Err(message);
+        }
+        if state.map.len() != len {
+            return Err(format!("state file {} is truncated", path.display()));
+        }
+        let resume = (0..config.workers)
+            .map(|i| state.get(Self::HEADER_WORDS + i))
+            .collect();
+        Ok((state, Some(resume)))
+    }
+
+    /// Word `i` of the file
+    fn get(&self, i: usize) -> usize {
+        let word = self.map[i * 8..i * 8 + 8].try_into();
+        u64::from_le_bytes(word.expect("words are 8 bytes")) as usize
+    }
+
+    fn set(&mut self, i: usize, word: usize) {
+        self.map[i * 8..i * 8 + 8].copy_from_slice(&(word as u64).to_le_bytes());
+    }
+
+    /// Stores where every worker continues from
+    fn save(&mut self, frontiers: &[usize]) {
+        for (i, &frontier) in frontiers.iter().enumerate() {
+            self.set(Self::HEADER_WORDS + i, frontier);
+        }
+        // the mapped pages outlive a killed process, flushing guards against a crashed system
+        if let Err(e) = self.map.flush_async() {
+            eprintln!("failed to save state file: {e}");
+        }
+    }
+
+    /// Moves the worker whose batch holds `n` back to the start of that batch
+    fn rewind(&self, frontiers: &mut [usize], n: usize) {
+        let batch = (n - self.start) / self.batch_size;
+        let worker = batch % frontiers.len();
+        frontiers[worker] = frontiers[worker].min(self.start + batch * self.batch_size);
+    }
+}
+
+/// Where a search resumes, the file holding one number per line
+struct Checkpoint {
+    /// everything below it was scanned
+    frontier: usize,
+    /// results at or past the frontier that were printed already
+    printed: BTreeSet<usize>,
+}
+
+/// Reads the number to resume from and the results printed past it,
+/// if the checkpoint file exists
+fn load_checkpoint(path: &Path) -> Result<Option<Checkpoint>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            let invalid = |e| format!("invalid checkpoint file {}: {e}", path.display());
+            let mut numbers = contents.lines().map(|line| line.trim().parse::<usize>());
+            let frontier = numbers
+                .next()
+                .unwrap_or_else(|| "".parse())
+                .map_err(invalid)?;
+            Ok(Some(Checkpoint {
+                frontier,
+                printed: numbers.collect::<Result<_, _>>().map_err(invalid)?,
+            }))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!(
+            "failed to read checkpoint file {}: {e}",
+            path.display()
+        )),
+    }
+}
+
+/// Replaces the checkpoint file with `frontier` and the results printed past it,
+/// forgetting those before it, warning on failure
+fn save_checkpoint(path: &Path, frontier: usize, printed: &Mutex<BTreeSet<usize>>) {
+    let mut contents = format!("{frontier}\n");
+    {
+        let mut printed = printed.lock().expect("printed results");
+        *printed = printed.split_off(&frontier);
+        for n in printed.iter() {
+            contents.push_str(&format!("{n}\n"));
+        }
+    }
+    // write to a temporary file first so an interruption never leaves a torn checkpoint
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let result = fs::write(&tmp, contents).and_then(|_| fs::rename(&tmp, path));
+    if let Err(e) = result {
+        eprintln!("failed to save checkpoint {}: {e}", path.display());
+    }
+}
+
+/// Formats a value with one decimal and an SI prefix, e.g. `12.4 M`
+fn si_prefixed(value: f64) -> String {
+    const PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+    let mut value = value;
+    let mut prefix = 0;
+    while value >= 1000.0 && prefix < PREFIXES.len() - 1 {
+        value /= 1000.0;
+        prefix += 1;
+    }
+    format!("{value:.1} {}", PREFIXES[prefix])
+}
+
+/// Formats a large count rounded to an integer, in scientific notation once it gets too long
+fn approximate(count: f64) -> String {
+    if count < 1e15 {
+        format!("{count:.0}")
+    } else {
+        format!("{count:.1e}")
+    }
+}
+
+/// Formats `seconds` in the largest unit that keeps the value readable
+fn human_duration(seconds: f64) -> String {
+    const UNITS: [(f64, &str); 4] = [
+        (365.25 * 86400.0, "years"),
+        (86400.0, "days"),
+        (3600.0, "h"),
+        (60.0, "min"),
+    ];
+    if !seconds.is_finite() {
+        return "forever".into();
+    }
+    match UNITS.iter().find(|(unit, _)| seconds >= *unit) {
+        Some((unit, "years")) if seconds / unit >= 1000.0 => {
+            format!("{:.1e} years", seconds / unit)
+        }
+        Some((unit, name)) => format!("{:.1} {name}", seconds / unit),
+        None => format!("{seconds:.1} s"),
+    }
+}
+
+/// Writes `fields` as a CSV row, each quoted where needed
+fn write_csv_row(out: &mut impl Write, fields: &[String]) -> io::Result<()> {
+    let row: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+    writeln!(out, "{}", row.join(","))
+}
+
+/// Quotes a CSV field if it contains characters with special meaning
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(flags: &[&str]) -> Args {
+        Args::from_args(&["hashsearch"], flags).expect("flags parse")
+    }
+
+    #[test]
+    fn num_zeros_up_to_the_digest_size() {
+        assert!(make_config(&args(&["-N", "64"])).is_ok());
+        assert_eq!(
+            make_config(&args(&["-N", "65"])).err().as_deref(),
+            Some("num_zeros 65 exceeds 64 nibbles for sha256")
+        );
+        #[cfg(feature = "sha512")]
+        assert!(make_config(&args(&["-N", "128", "-a", "sha512"])).is_ok());
+    }
+
+    #[test]
+    fn dry_run_validates() {
+        let args = args(&["-N", "4", "-W", "0"]);
+        let config = make_config(&args).expect("flags are valid");
+        assert_eq!(
+            dry_run(&args, &config).unwrap_err().to_string(),
+            "invalid search config: at least 1 worker is required"
+        );
+    }
+
+    #[test]
+    fn lines_stay_whole_with_many_workers() {
+        let args = args(&["-N", "1", "-K", "500", "-W", "8", "--batch", "1"]);
+        let config = make_config(&args).expect("flags are valid");
+        let mut out = Vec::new();
+        assert_eq!(print_results(&args, config, &mut out, false), 0);
+        let out = String::from_utf8(out).expect("output is text");
+        let mut seen = BTreeSet::new();
+        for line in out.lines() {
+            let (n, hash) = line.split_once(": ").expect("line has an n and a hash");
+            assert!(
+                seen.insert(n.parse::<usize>().expect("n is a number")),
+                "{line}"
+            );
+            assert_eq!(hash.len(), 64, "{line}");
+            assert!(hash.bytes().all(|b| b.is_ascii_hexdigit()), "{line}");
+            assert!(hash.ends_with('0'), "{line}");
+        }
+        assert_eq!(seen.len(), 500);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn rayon_rejects_what_it_ignores() {
+        for flags in [
+            &["--pin"][..],
+            &["--pin", "--numa"],
+            &["--best"],
+            &["--histogram"],
+        ] {
+            let args = args(&[&["--backend", "rayon", "--end", "9"], flags].concat());
+            assert!(make_config(&args).is_err());
+        }
+    }
+
+    #[test]
+    fn scanned_bound_only_for_ascending_sequential_searches() {
+        assert!(args(&["-N", "3"]).scans_below_frontier());
+        for flags in [&["--descending"][..], &["--random"], &["--stdin"]] {
+            assert!(!args(&[&["-N", "3"], flags].concat()).scans_below_frontier());
+        }
+    }
+
+    #[test]
+    fn csv_header_matches_the_rows() {
+        // a repeated -N is a single target without a zeros column, distinct ones add it
+        for targets in [["-N", "2", "-N", "2"], ["-N", "2", "-N", "1"]] {
+            let args = args(&[&targets[..], &["--format", "csv", "-K", "3"]].concat());
+            let config = make_config(&args).expect("flags are valid");
+            let mut out = Vec::new();
+            assert_eq!(print_results(&args, config, &mut out, false), 0);
+            let out = String::from_utf8(out).expect("output is text");
+            let fields: Vec<_> = out.lines().map(|line| line.split(',').count()).collect();
+            // -K counts every target on its own
+            assert!(fields.len() > 3, "{out}");
+            assert!(fields.iter().all(|&n| n == fields[0]), "{out}");
+        }
+    }
+
+    /// Digest of `n` under the config the flags produce, in hex
+    fn digest(flags: &[&str], n: usize) -> String {
+        let args = args(flags);
+        let config = make_config(&args).expect("flags are valid");
+        args.algo.verify(&config, n).unwrap().0.to_string()
+    }
+
+    #[test]
+    fn big_endian_digest() {
+        // sha256 of 12345 as 4 big-endian bytes, 00003039
+        assert_eq!(
+            digest(&["-N", "1", "--big-endian", "--width", "4"], 12345),
+            "6b12f6f5b9234b3206c99bf2c25dc463da1fa11327f17215df2a708e10a557ca"
+        );
+    }
+
+    #[test]
+    fn ascii_digest() {
+        assert_eq!(
+            digest(&["-N", "1", "--ascii"], 12345),
+            "5994471abb01112afcc18159f6cc74b4f511b99806da59b3caf5a9c173cacfc5"
+        );
+    }
+
+    #[test]
+    fn padded_digest() {
+        assert_eq!(
+            digest(&["-N", "1", "--ascii", "--pad-width", "8"], 12345),
+            "a24e4232b7275b9cf5f2badb07103ec4a29ad896f061d5abfdec4b2499c8b095"
+        );
+        assert!(make_config(&args(&[
+            "-N",
+            "1",
+            "--ascii",
+            "--pad-width",
+            "4",
+            "--start",
+            "12345"
+        ]))
+        .is_err());
+    }
 }
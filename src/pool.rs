@@ -0,0 +1,110 @@
+use crate::{
+    encoding::MAX_ENCODED_SIZE,
+    worker::{results_channel, Candidates, Field, Tester},
+    CancelToken, Config, Progress, SearchError, Workers,
+};
+use rayon::{prelude::*, ThreadPool};
+use sha2::{
+    digest::{core_api::BlockSizeUser, FixedOutputReset},
+    Digest,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// Like [`crate::spawn_workers`], but hashes batches as tasks on a rayon thread pool
+///
+/// Idle pool threads steal batches, so the order in which they are finished is arbitrary
+/// and nothing is known to be scanned until the search ends:
+/// [`Progress::frontier`] stays at the start of the range.
+/// [`Progress::worker_stats`] are kept for each thread of the pool.
+/// `config.workers` and `config.pin` are ignored in favor of the pool's own settings.
+/// Fails if `config` does not [validate](Config::validate), or has a seed or `resume`.
+pub fn spawn_pool_workers<D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send + 'static>(
+    config: Config,
+    pool: Arc<ThreadPool>,
+) -> Result<Workers, SearchError> {
+    config.validate_for::<D>(&[Field::Seed, Field::Resume])?;
+    let Config {
+        checks,
+        start,
+        end,
+        step,
+        descending,
+        encoding,
+        width,
+        prefix_bytes,
+        double,
+        hmac_key,
+        also,
+        channel_capacity,
+        overflow,
+        cancel,
+        batch_size,
+        ..
+    } = config;
+
+    let stop = CancelToken::flag(cancel);
+    let progress = Arc::new(Progress::new(
+        Box::new([AtomicUsize::new(start)]),
+        pool.current_num_threads(),
+    ));
+    let (result_tx, result_rx) = results_channel(channel_capacity, overflow, &stop, &progress);
+
+    let th_stop = stop.clone();
+    let th_progress = progress.clone();
+    // the pool is driven from a dedicated thread so `Workers` can be joined like usual
+    let handle = thread::spawn(move || {
+        let candidates = Candidates::new(start, end, step, descending);
+        let batches = (0..candidates.len).step_by(batch_size);
+        pool.install(|| {
+            let scanned = batches.par_bridge().try_for_each_init(
+                || {
+                    let tester = Tester::<D>::new(
+                        &checks,
+                        &prefix_bytes,
+                        double,
+                        hmac_key.as_deref(),
+                        &also,
+                    );
+                    (tester, Vec::new())
+                },
+                |(tester, results_buf), batch_start| {
+                    if th_stop.load(Ordering::Relaxed) {
+                        return Err(());
+                    }
+                    let batch_end = candidates.len.min(batch_start.saturating_add(batch_size));
+                    let mut input = [0; MAX_ENCODED_SIZE];
+                    for position in batch_start..batch_end {
+                        let n = candidates.at(position);
+                        let candidate = encoding.encode(n, width, &mut input);
+                        if let Some(check) = tester.test(candidate) {
+                            results_buf.push(tester.found(n, position + 1, check, candidate));
+                        }
+                    }
+
+                    let thread = rayon::current_thread_index().unwrap_or_default();
+                    let hashed = batch_end - batch_start;
+                    if result_tx.finish_batch(thread, hashed, results_buf) {
+                        Ok(())
+                    } else {
+                        Err(())
+                    }
+                },
+            );
+            if scanned.is_ok() {
+                th_progress.finish();
+            }
+        });
+    });
+    Ok(Workers {
+        handles: vec![handle],
+        results: result_rx,
+        stop,
+        progress,
+    })
+}
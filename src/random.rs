@@ -0,0 +1,141 @@
+use crate::{
+    encoding::MAX_ENCODED_SIZE,
+    worker::{core_for, pin_to, results_channel, Tester},
+    CancelToken, Config, Progress, Workers,
+};
+use log::info;
+use sha2::{
+    digest::{core_api::BlockSizeUser, FixedOutputReset},
+    Digest,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// Candidates drawn per candidate in the range before the range is taken as exhausted,
+/// by then each one is left out with a chance of e^-32, about 10^-14
+const DRAWS_PER_CANDIDATE: usize = 32;
+
+/// SplitMix64, small and fast, with a fixed output for every seed
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform number below `bound`, which must not be 0
+    fn below(&mut self, bound: usize) -> usize {
+        // scaling is cheaper than `%`, both are biased by at most bound / 2^64
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+}
+
+/// Like [`crate::spawn_workers`], but every worker draws candidates at random from the range
+/// with a generator seeded from `seed`
+///
+/// The same seed and number of workers draw the same candidates,
+/// though results arrive in whatever order workers find them.
+/// Candidates may be drawn more than once, so a result can repeat,
+/// which is unlikely unless the range is small.
+/// Workers stop after drawing [`DRAWS_PER_CANDIDATE`] times as many candidates as the range
+/// holds, so a bounded range is likely exhausted when [`Progress::exhausted`] is set,
+/// while an unbounded one never is. [`Progress::frontier`] stays at its start.
+/// [`Found::attempts`] is approximate, counting the hashes of finished batches.
+/// `config` must be validated already.
+pub(crate) fn spawn_random_workers<D>(config: Config, seed: u64) -> Workers
+where
+    D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send + 'static,
+{
+    let Config {
+        checks,
+        workers,
+        start,
+        end,
+        encoding,
+        width,
+        prefix_bytes,
+        double,
+        hmac_key,
+        also,
+        channel_capacity,
+        overflow,
+        pin,
+        numa,
+        cancel,
+        batch_size,
+        ..
+    } = config;
+
+    let stop = CancelToken::flag(cancel);
+    let end = end.unwrap_or(usize::MAX);
+    let len = end.saturating_sub(start).max(1);
+    // rounded up, so the workers together draw at least that many
+    let quota = len.saturating_mul(DRAWS_PER_CANDIDATE).div_ceil(workers);
+    info!("spawning {workers} workers drawing from {start}..{end} with seed {seed}");
+    let progress = Arc::new(Progress::new(
+        (0..workers).map(|_| AtomicUsize::new(start)).collect(),
+        workers,
+    ));
+    let (result_tx, result_rx) = results_channel(channel_capacity, overflow, &stop, &progress);
+    // every worker gets its own stream, seeded in turn so they do not overlap
+    let mut seeds = SplitMix64::new(seed);
+    let setup = Arc::new((checks, prefix_bytes, hmac_key, also));
+
+    let handles = (0..workers).map(|i| {
+        let th_result_tx = result_tx.clone();
+        let th_stop = stop.clone();
+        let th_progress = progress.clone();
+        let th_setup = setup.clone();
+        let mut rng = SplitMix64::new(seeds.next_u64());
+        let core = core_for(pin, numa, i);
+
+        thread::spawn(move || {
+            pin_to(core);
+            let (checks, prefix_bytes, hmac_key, also) = &*th_setup;
+            let mut tester =
+                Tester::<D>::new(checks, prefix_bytes, double, hmac_key.as_deref(), also);
+            let mut results_buf = Vec::new();
+            let mut input = [0; MAX_ENCODED_SIZE];
+            let mut left = quota;
+            while !th_stop.load(Ordering::Relaxed) {
+                if left == 0 {
+                    th_progress.finish();
+                    return;
+                }
+                let batch_size = batch_size.min(left);
+                left -= batch_size;
+                let hashed = th_progress.hashes() as usize;
+                for drawn in 1..=batch_size {
+                    let n = start + rng.below(len);
+                    let candidate = encoding.encode(n, width, &mut input);
+                    if let Some(check) = tester.test(candidate) {
+                        results_buf.push(tester.found(n, hashed + drawn, check, candidate));
+                    }
+                }
+
+                if !th_result_tx.finish_batch(i, batch_size, &mut results_buf) {
+                    return;
+                }
+            }
+        })
+    });
+    Workers {
+        handles: handles.collect(),
+        results: result_rx,
+        stop,
+        progress,
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{Found, Progress, Workers};
+use crossbeam_channel::{Receiver, RecvError, RecvTimeoutError};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Receives results of a search in strictly ascending order of `n`
+///
+/// A result is held back until every worker has scanned past it,
+/// so memory grows with the number of results found ahead of the slowest worker.
+/// With evenly loaded workers that is about one batch per worker,
+/// but a stalled worker makes everything found by the others pile up.
+pub struct SortedResults<'a> {
+    results: &'a Receiver<Found>,
+    progress: Arc<Progress>,
+    pending: BTreeMap<usize, Found>,
+}
+
+impl<'a> SortedResults<'a> {
+    /// How often the frontier is rechecked while results are held back
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    pub fn new(workers: &'a Workers) -> Self {
+        Self {
+            results: workers.results(),
+            progress: workers.progress(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Blocks until the next result in order is known,
+    /// fails once the workers have exited and every result was received
+    pub fn recv(&mut self) -> Result<Found, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    /// Like [`Self::recv`], but gives up once `deadline` passes
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<Found, RecvTimeoutError> {
+        self.recv_until(Some(deadline))
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<Found, RecvTimeoutError> {
+        loop {
+            // results below the frontier are already in the channel
+            let frontier = self.progress.frontier();
+            for found in self.results.try_iter() {
+                self.pending.insert(found.n, found);
+            }
+            let now = Instant::now();
+            match self.pending.first_entry() {
+                Some(first) if *first.key() < frontier => return Ok(first.remove()),
+                _ if deadline.is_some_and(|deadline| deadline <= now) => {
+                    return Err(RecvTimeoutError::Timeout)
+                }
+                Some(_) => {
+                    let timeout = deadline.map_or(Self::POLL_INTERVAL, |deadline| {
+                        Self::POLL_INTERVAL.min(deadline - now)
+                    });
+                    match self.results.recv_timeout(timeout) {
+                        Ok(found) => {
+                            self.pending.insert(found.n, found);
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => {
+                            return self
+                                .pending
+                                .pop_first()
+                                .map(|(_, f)| f)
+                                .ok_or(RecvTimeoutError::Disconnected)
+                        }
+                    }
+                }
+                None => {
+                    let found = match deadline {
+                        Some(deadline) => self.results.recv_deadline(deadline)?,
+                        None => self.results.recv()?,
+                    };
+                    self.pending.insert(found.n, found);
+                }
+            }
+        }
+    }
+
+    /// Results that were received but held back
+    pub fn pending(&self) -> impl Iterator<Item = &Found> {
+        self.pending.values()
+    }
+}
@@ -0,0 +1,1530 @@
+use crate::{
+    check::{to_words, Check, Direction, Words, MAX_DIGEST_SIZE},
+    encoding::{Encoding, HexBytes, MAX_ENCODED_SIZE, MAX_WIDTH},
+    lanes::{Sha256Lanes, LANES},
+    random::spawn_random_workers,
+    Algo, SearchError,
+};
+use core_affinity::CoreId;
+use crossbeam_channel::{bounded, unbounded, Receiver, SendTimeoutError, Sender, TrySendError};
+use log::{debug, info};
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{
+    digest::{core_api::BlockSizeUser, FixedOutputReset, Output},
+    Digest, Sha256,
+};
+use std::{
+    any::TypeId,
+    fmt, fs,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Default for [`Config::batch_size`]
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Batch size at which a worker expects about one result per batch of `checks`
+/// on `digest_size` byte digests, from [`DEFAULT_BATCH_SIZE`] to 65536
+///
+/// Rare results favor large batches, which spend less time checking in,
+/// frequent ones small batches, which deliver results sooner and buffer fewer of them.
+pub fn adaptive_batch_size(checks: &[Check], digest_size: usize) -> usize {
+    const MAX_ADAPTIVE_BATCH_SIZE: usize = 1 << 16;
+    let probability = checks
+        .iter()
+        .map(|check| check.probability(digest_size))
+        .fold(0.0, f64::max);
+    (1.0 / probability).clamp(DEFAULT_BATCH_SIZE as f64, MAX_ADAPTIVE_BATCH_SIZE as f64) as usize
+}
+
+/// Default for [`Config::channel_capacity`]
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 4096;
+
+/// [`Config::channel_capacity`] of a channel holding any number of results
+pub const UNBOUNDED_CHANNEL: usize = usize::MAX;
+
+/// What workers do with results once the channel is full
+#[derive(Clone, Copy, Default)]
+pub enum Overflow {
+    /// Wait for results to be received, pausing the search
+    #[default]
+    Wait,
+    /// Drop the oldest result in the channel to make room, counted by [`Progress::dropped`]
+    DropOldest,
+}
+
+/// How often a waiting worker checks whether the search was stopped
+pub(crate) const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Candidate whose digest passed the check
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Found {
+    /// The candidate integer, or line number of a candidate line
+    pub n: usize,
+    /// The candidate line, if lines are hashed instead of integers
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub line: Option<String>,
+    /// Number of candidates up to and including this one,
+    /// counted from the start of the range or input
+    pub attempts: usize,
+    /// Index of the first of [`Config::checks`] the digest passed
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub check: usize,
+    pub hash: DigestBytes,
+    /// Exact bytes hashed: the prefix bytes followed by the encoded candidate or line,
+    /// the message authenticated with HMAC
+    pub input: HexBytes,
+    /// Digests of the further algorithms of [`Config::also`], in the same order
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub also: Vec<DigestBytes>,
+}
+
+/// Raw digest of a found candidate, displayed and serialized as lowercase hex,
+/// deserialized from hex of either case
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DigestBytes {
+    bytes: [u8; MAX_DIGEST_SIZE],
+    len: u8,
+}
+
+impl DigestBytes {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    /// Number of zero nibbles the hex digest starts or ends with
+    pub fn zero_nibbles(&self, direction: Direction) -> usize {
+        zero_nibbles(self.as_bytes(), direction)
+    }
+}
+
+impl DigestBytes {
+    fn write_hex(&self, f: &mut fmt::Formatter<'_>, digits: &[u8; 16]) -> fmt::Result {
+        let mut hex = [0; MAX_DIGEST_SIZE * 2];
+        for (pair, b) in hex.chunks_exact_mut(2).zip(self.as_bytes()) {
+            pair[0] = digits[(b >> 4) as usize];
+            pair[1] = digits[(b & 0xf) as usize];
+        }
+        let hex = &hex[..self.as_bytes().len() * 2];
+        f.write_str(std::str::from_utf8(hex).expect("hex digits are ASCII"))
+    }
+}
+
+impl fmt::LowerHex for DigestBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_hex(f, b"0123456789abcdef")
+    }
+}
+
+impl fmt::UpperHex for DigestBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_hex(f, b"0123456789ABCDEF")
+    }
+}
+
+impl fmt::Display for DigestBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl fmt::Debug for DigestBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for DigestBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for DigestBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let HexBytes(bytes) = hex.parse().map_err(de::Error::custom)?;
+        if bytes.len() > MAX_DIGEST_SIZE {
+            return Err(de::Error::custom(format!(
+                "digest `{hex}` is longer than {MAX_DIGEST_SIZE} bytes"
+            )));
+        }
+        let mut digest = Self {
+            bytes: [0; MAX_DIGEST_SIZE],
+            len: bytes.len() as u8,
+        };
+        digest.bytes[..bytes.len()].copy_from_slice(&bytes);
+        Ok(digest)
+    }
+}
+
+/// Parameters of a search
+#[derive(Clone)]
+pub struct Config {
+    /// Requirements a digest has to meet, at least one of them
+    ///
+    /// Each result reports the first check it passes,
+    /// so stronger checks go first when they overlap.
+    pub checks: Vec<Check>,
+    /// Number of worker threads, must not be 0
+    pub workers: usize,
+    /// First candidate to hash
+    pub start: usize,
+    /// Candidate to stop before, the search runs up to `usize::MAX` if `None`,
+    /// or down to 0 inclusive if `descending`
+    pub end: Option<usize>,
+    /// Distance between consecutive candidates, must not be 0
+    ///
+    /// Candidates are `start`, `start + step` and so on while they are before `end`.
+    /// Does not apply to lines or with a seed.
+    pub step: usize,
+    /// Count down from `start` instead of up, `end` being below it
+    ///
+    /// Frontiers then count down as well, so [`Progress::frontier`], [`crate::SortedResults`]
+    /// and `resume` do not apply.
+    /// Does not apply to lines or with a seed.
+    pub descending: bool,
+    /// How candidates are serialized before hashing
+    pub encoding: Encoding,
+    /// Number of bytes binary encodings take, at most [`MAX_WIDTH`],
+    /// or digits [`Encoding::PaddedDecimal`] pads to, at most [`MAX_ENCODED_SIZE`]
+    ///
+    /// Candidates must fit, higher bytes are dropped otherwise.
+    pub width: usize,
+    /// Bytes hashed before every candidate
+    pub prefix_bytes: Vec<u8>,
+    /// Hash every digest once more before checking it, e.g. Bitcoin's double SHA-256
+    pub double: bool,
+    /// Key to hash candidates with HMAC instead, the prefix bytes starting the message
+    ///
+    /// Excludes `double` and needs the `hmac` feature.
+    pub hmac_key: Option<Vec<u8>>,
+    /// Further algorithms whose digests of a candidate must pass the checks as well,
+    /// along with checks for their digest size, one for each of `checks` in the same order
+    ///
+    /// They are only hashed for candidates whose digests passed the ones before,
+    /// so they cost little unless results are common.
+    pub also: Vec<(Algo, Vec<Check>)>,
+    /// Number of results the channel holds before workers wait for them to be received,
+    /// or [`UNBOUNDED_CHANNEL`] for workers to never wait at the cost of unbounded memory
+    ///
+    /// Workers send results as they finish their batches,
+    /// so they are not received in ascending order either way,
+    /// [`crate::SortedResults`] restores it.
+    pub channel_capacity: usize,
+    /// What workers do once `channel_capacity` results wait to be received
+    ///
+    /// Results buffered by workers until their batch is done add up to a batch each.
+    pub overflow: Overflow,
+    /// Pin each worker thread to a distinct core, where supported
+    pub pin: bool,
+    /// With `pin`, fill the cores of one NUMA node before moving on to the next,
+    /// where Linux reports the topology
+    ///
+    /// Workers set up their hashing state on the pinned thread,
+    /// so with `pin` it is allocated on the worker's node either way.
+    pub numa: bool,
+    /// Draw candidates at random from the range with generators seeded from this,
+    /// instead of scanning it in order
+    ///
+    /// Only applies to [`spawn_workers`].
+    pub seed: Option<u64>,
+    /// Token that stops the search once cancelled, from any thread
+    pub cancel: Option<CancelToken>,
+    /// Number of candidates a worker hashes between checking in, must not be 0
+    ///
+    /// Larger batches spend less time on bookkeeping,
+    /// smaller ones deliver results and react to being stopped sooner,
+    /// [`adaptive_batch_size`] picks one from the checks.
+    pub batch_size: usize,
+    /// Candidate each worker continues from, one per worker,
+    /// as [`Progress::worker_frontiers`] left them in a search of the same range and batch size
+    ///
+    /// Only applies to [`spawn_workers`] without a seed.
+    pub resume: Option<Vec<usize>>,
+}
+
+impl Config {
+    /// Unbounded search starting at 1, hashing little-endian candidates
+    pub fn new(check: Check, workers: usize) -> Self {
+        Self {
+            checks: vec![check],
+            workers,
+            start: 1,
+            end: None,
+            step: 1,
+            descending: false,
+            encoding: Encoding::Le,
+            width: size_of::<usize>(),
+            prefix_bytes: Vec::new(),
+            double: false,
+            hmac_key: None,
+            also: Vec::new(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            overflow: Overflow::Wait,
+            pin: false,
+            numa: false,
+            seed: None,
+            cancel: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            resume: None,
+        }
+    }
+
+    /// Checks that the fields are in range and do not contradict each other
+    pub fn validate(&self) -> Result<(), SearchError> {
+        if self.workers == 0 {
+            return Err(SearchError::InvalidConfig("at least 1 worker is required"));
+        }
+        if self.batch_size == 0 {
+            return Err(SearchError::InvalidConfig("batch size must not be 0"));
+        }
+        if self.step == 0 {
+            return Err(SearchError::InvalidConfig("step must not be 0"));
+        }
+        if self
+            .resume
+            .as_ref()
+            .is_some_and(|resume| resume.len() != self.workers)
+        {
+            return Err(SearchError::InvalidConfig(
+                "resuming needs one candidate for each worker",
+            ));
+        }
+        if let Encoding::PaddedDecimal = self.encoding {
+            if self.width == 0 || self.width > MAX_ENCODED_SIZE {
+                return Err(SearchError::InvalidConfig("pad width must be from 1 to 32"));
+            }
+            // wider candidates would not be padded to the same length
+            if let Some(limit) = 10usize.checked_pow(self.width as u32) {
+                let unbounded = self.end.is_none() && !self.descending;
+                if self.start >= limit || unbounded || self.end.is_some_and(|end| end > limit) {
+                    return Err(SearchError::InvalidConfig(
+                        "candidates must fit in the pad width",
+                    ));
+                }
+            }
+        } else if self.width == 0 || self.width > MAX_WIDTH {
+            return Err(SearchError::InvalidConfig("width must be from 1 to 16"));
+        }
+        if let (Overflow::DropOldest, 0 | UNBOUNDED_CHANNEL) =
+            (self.overflow, self.channel_capacity)
+        {
+            return Err(SearchError::InvalidConfig(
+                "dropping the oldest results needs a bounded channel with room for some",
+            ));
+        }
+        if self.hmac_key.is_some() && !cfg!(feature = "hmac") {
+            return Err(SearchError::InvalidConfig("HMAC needs the hmac feature"));
+        }
+        if self.double && self.hmac_key.is_some() {
+            return Err(SearchError::InvalidConfig(
+                "double hashing cannot be combined with HMAC",
+            ));
+        }
+        if self
+            .also
+            .iter()
+            .any(|(_, checks)| checks.len() != self.checks.len())
+        {
+            return Err(SearchError::InvalidConfig(
+                "every further algorithm needs one check for each of `checks`",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Like [`Self::validate`], also requiring digests of `D` to fit in [`MAX_DIGEST_SIZE`] bytes
+    /// and the `unsupported` fields to be left unset
+    pub(crate) fn validate_for<D: Digest>(&self, unsupported: &[Field]) -> Result<(), SearchError> {
+        self.validate()?;
+        self.reject(unsupported)?;
+        if <D as Digest>::output_size() > MAX_DIGEST_SIZE {
+            return Err(SearchError::InvalidConfig(
+                "digests longer than 64 bytes are not supported",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails if any of the `unsupported` fields is set
+    pub(crate) fn reject(&self, unsupported: &[Field]) -> Result<(), SearchError> {
+        match unsupported.iter().find(|field| field.is_set(self)) {
+            Some(field) => Err(SearchError::InvalidConfig(field.unsupported())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fields of [`Config`] only some searches support, which the others reject when set
+#[derive(Clone, Copy)]
+pub(crate) enum Field {
+    /// [`Config::step`] other than 1 or [`Config::descending`]
+    Sequence,
+    Seed,
+    Resume,
+    Also,
+}
+
+impl Field {
+    fn is_set(self, config: &Config) -> bool {
+        match self {
+            Self::Sequence => config.step != 1 || config.descending,
+            Self::Seed => config.seed.is_some(),
+            Self::Resume => config.resume.is_some(),
+            Self::Also => !config.also.is_empty(),
+        }
+    }
+
+    /// Reason a search that does not support the field gives
+    fn unsupported(self) -> &'static str {
+        match self {
+            Self::Sequence => "this search does not support a step or descending candidates",
+            Self::Seed => "this search does not support random candidates",
+            Self::Resume => "this search cannot resume",
+            Self::Also => "this search does not support further algorithms",
+        }
+    }
+}
+
+/// Work done by a single worker thread
+#[derive(Clone, Copy)]
+pub struct WorkerStats {
+    /// Number of candidates hashed
+    pub hashes: u64,
+    /// Number of candidates that passed a check
+    pub found: u64,
+}
+
+/// Counters shared between workers and observers of a search
+pub struct Progress {
+    hashes: AtomicU64,
+    /// start of the next batch of each worker
+    pub(crate) frontiers: Box<[AtomicUsize]>,
+    /// number of workers that ran out of candidates
+    finished: AtomicUsize,
+    /// hashes and found candidates of each worker thread
+    per_worker: Box<[(AtomicU64, AtomicU64)]>,
+    dropped: AtomicU64,
+}
+
+impl Progress {
+    pub(crate) fn new(frontiers: Box<[AtomicUsize]>, threads: usize) -> Self {
+        Self {
+            hashes: AtomicU64::new(0),
+            frontiers,
+            finished: AtomicUsize::new(0),
+            per_worker: (0..threads).map(|_| Default::default()).collect(),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Records a batch finished by worker thread `worker`
+    pub(crate) fn record_batch(&self, worker: usize, hashes: usize, found: usize) {
+        self.hashes.fetch_add(hashes as u64, Ordering::Relaxed);
+        if let Some((worker_hashes, worker_found)) = self.per_worker.get(worker) {
+            worker_hashes.fetch_add(hashes as u64, Ordering::Relaxed);
+            worker_found.fetch_add(found as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Work done by each worker thread so far, updated once per batch
+    pub fn worker_stats(&self) -> Vec<WorkerStats> {
+        self.per_worker
+            .iter()
+            .map(|(hashes, found)| WorkerStats {
+                hashes: hashes.load(Ordering::Relaxed),
+                found: found.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Records that a worker ran out of candidates
+    pub(crate) fn finish(&self) {
+        self.finished.fetch_add(1, Ordering::Release);
+    }
+
+    /// Whether every worker ran out of candidates,
+    /// as opposed to being stopped or dying
+    pub fn exhausted(&self) -> bool {
+        self.finished.load(Ordering::Acquire) == self.frontiers.len()
+    }
+
+    /// Number of results dropped to make room for newer ones, with [`Overflow::DropOldest`]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Number of candidates hashed so far, updated once per batch
+    pub fn hashes(&self) -> u64 {
+        self.hashes.load(Ordering::Relaxed)
+    }
+
+    /// Start of the next batch of each worker, all of its batches before it were hashed
+    /// and their results sent
+    pub fn worker_frontiers(&self) -> Vec<usize> {
+        self.frontiers
+            .iter()
+            .map(|f| f.load(Ordering::Acquire))
+            .collect()
+    }
+
+    /// Lowest candidate that is not known to be scanned,
+    /// all candidates before it were hashed and their results sent
+    pub fn frontier(&self) -> usize {
+        self.frontiers
+            .iter()
+            .map(|f| f.load(Ordering::Acquire))
+            .min()
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/// Arithmetic sequence of candidates, addressed by their position in it
+#[derive(Clone, Copy)]
+pub(crate) struct Candidates {
+    start: usize,
+    step: usize,
+    descending: bool,
+    /// number of candidates, positions below it never overflow
+    pub(crate) len: usize,
+}
+
+impl Candidates {
+    pub(crate) fn new(start: usize, end: Option<usize>, step: usize, descending: bool) -> Self {
+        let len = match (descending, end) {
+            // an unbounded search stops short of the last candidate rather than overflowing
+            (false, end) => end
+                .unwrap_or(usize::MAX)
+                .saturating_sub(start)
+                .div_ceil(step),
+            (true, Some(end)) => start.saturating_sub(end).div_ceil(step),
+            (true, None) => start / step + 1,
+        };
+        Self {
+            start,
+            step,
+            descending,
+            len,
+        }
+    }
+
+    /// Candidate at `position`, which must be below `len`
+    #[inline]
+    pub(crate) fn at(&self, position: usize) -> usize {
+        if self.descending {
+            self.start - position * self.step
+        } else {
+            self.start + position * self.step
+        }
+    }
+
+    /// Candidate at `position` as a frontier, saturating past the end of the counter
+    pub(crate) fn frontier(&self, position: usize) -> usize {
+        let distance = position.saturating_mul(self.step);
+        if self.descending {
+            self.start.saturating_sub(distance)
+        } else {
+            self.start.saturating_add(distance)
+        }
+    }
+
+    /// Position of the first candidate at or past the frontier `n`
+    fn position(&self, n: usize) -> usize {
+        let distance = if self.descending {
+            self.start.saturating_sub(n)
+        } else {
+            n.saturating_sub(self.start)
+        };
+        distance.div_ceil(self.step)
+    }
+
+    /// Batch loop of one of `workers` workers taking turns at batches of `batch_size` positions,
+    /// from its first batch at `first_batch` until the sequence is exhausted or `stop` is set
+    ///
+    /// `batch` hashes the positions of a batch, given along with the position after the batches
+    /// of the other workers, and returns false if the worker has to stop.
+    /// Returns the position of the batch the worker did not finish,
+    /// at least `len` if it got through its part of the sequence.
+    pub(crate) fn scan(
+        &self,
+        first_batch: usize,
+        batch_size: usize,
+        workers: usize,
+        stop: &AtomicBool,
+        mut batch: impl FnMut(Range<usize>, usize) -> bool,
+    ) -> usize {
+        let mut batch_start = first_batch;
+        while !stop.load(Ordering::Relaxed) && batch_start < self.len {
+            let batch_end = self.len.min(batch_start.saturating_add(batch_size));
+            // saturating ends the search at the end of the counter instead of wrapping
+            let next = batch_start.saturating_add(batch_size.saturating_mul(workers));
+            if !batch(batch_start..batch_end, next) {
+                break;
+            }
+            batch_start = next;
+        }
+        batch_start
+    }
+}
+
+/// Cloneable handle that stops a search from any thread
+///
+/// Workers notice once they finish their current batch,
+/// or within a stop poll while they wait on a full channel or for input.
+/// The search cancels its token itself when it stops, so every search needs a fresh one.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the search the token was passed to
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Stop flag for workers, shared with `token` if there is one
+    pub(crate) fn flag(token: Option<CancelToken>) -> Arc<AtomicBool> {
+        token.map(|token| token.0).unwrap_or_default()
+    }
+}
+
+/// Running worker threads along with the channel they send results to
+///
+/// Dropping it stops the workers and waits for them to exit,
+/// which takes at most one batch, or one stop poll if they wait on a full channel.
+pub struct Workers {
+    pub(crate) results: Receiver<Found>,
+    pub(crate) handles: Vec<JoinHandle<()>>,
+    pub(crate) stop: Arc<AtomicBool>,
+    pub(crate) progress: Arc<Progress>,
+}
+
+impl Workers {
+    /// Channel the workers send found candidates to
+    pub fn results(&self) -> &Receiver<Found> {
+        &self.results
+    }
+
+    /// Counters updated by the workers as they go
+    pub fn progress(&self) -> Arc<Progress> {
+        self.progress.clone()
+    }
+
+    /// Flag that stops the workers once set, e.g. from a signal handler,
+    /// which then exit and disconnect the results channel
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Whether the workers were told to stop, through the flag or a [`CancelToken`]
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Signals the workers to stop and waits for them to exit,
+    /// returning results that were sent but not yet received
+    pub fn stop(mut self) -> Vec<Found> {
+        self.shutdown();
+        self.results.try_iter().collect()
+    }
+
+    fn shutdown(&mut self) {
+        if !self.handles.is_empty() {
+            debug!(
+                "stopping {} workers after {} hashes",
+                self.handles.len(),
+                self.progress.hashes()
+            );
+        }
+        self.stop.store(true, Ordering::Relaxed);
+        for handle in self.handles.drain(..) {
+            // a panicked worker has nothing left to clean up
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Hashing state of a worker thread, testing one candidate at a time
+pub(crate) struct Tester<D> {
+    hasher: D,
+    /// state with the prefix bytes absorbed
+    seeded: D,
+    has_prefix: bool,
+    prefix_bytes: Box<[u8]>,
+    /// state the first digest is hashed again from, if there is a second round
+    outer: Option<D>,
+    hash: [u8; MAX_DIGEST_SIZE],
+    checks: Box<[Check]>,
+    digest_size: usize,
+    words: usize,
+    /// testers of [`Config::also`], hashing a candidate only once it passed those before
+    also: Vec<Box<dyn Rehash>>,
+}
+
+/// [`Tester`] of an algorithm chosen at runtime, for the algorithms of [`Config::also`]
+pub(crate) trait Rehash: Send {
+    /// Hashes `input` the way [`Tester::test`] does, without checking the digest
+    fn hash(&mut self, input: &[u8]);
+    /// Whether the last digest passes check `check`
+    fn passes(&self, check: usize) -> bool;
+    /// The last digest
+    fn digest(&self) -> DigestBytes;
+}
+
+impl<D: Digest + FixedOutputReset + BlockSizeUser + Clone> Tester<D> {
+    pub(crate) fn new(
+        checks: &[Check],
+        prefix_bytes: &[u8],
+        double: bool,
+        hmac_key: Option<&[u8]>,
+        also: &[(Algo, Vec<Check>)],
+    ) -> Self {
+        assert!(
+            !double || hmac_key.is_none(),
+            "double hashing cannot be combined with HMAC"
+        );
+        let digest_size = <D as Digest>::output_size();
+        // the prefix is constant, so it is only absorbed once
+        // and the absorbed state is restored after every candidate
+        let mut seeded = D::new();
+        #[cfg(feature = "hmac")]
+        let outer = match hmac_key {
+            // HMAC as in RFC 2104, with both padded keys absorbed up front
+            Some(key) => {
+                let (inner_pad, outer_pad) = hmac_pads::<D>(key);
+                Digest::update(&mut seeded, inner_pad);
+                Some(D::new_with_prefix(outer_pad))
+            }
+            None => double.then(D::new),
+        };
+        #[cfg(not(feature = "hmac"))]
+        let outer = double.then(D::new);
+        Digest::update(&mut seeded, prefix_bytes);
+        Self {
+            hasher: seeded.clone(),
+            seeded,
+            has_prefix: !prefix_bytes.is_empty() || hmac_key.is_some(),
+            prefix_bytes: prefix_bytes.into(),
+            outer,
+            hash: [0; MAX_DIGEST_SIZE],
+            checks: checks.into(),
+            digest_size,
+            words: digest_size.div_ceil(8),
+            also: also
+                .iter()
+                .map(|(algo, checks)| algo.rehasher(checks, prefix_bytes, double, hmac_key))
+                .collect(),
+        }
+    }
+
+    /// Hashes the prefix bytes followed by `input`, then the digest again if doubling or HMAC,
+    /// and returns the index of the first check the digest passes
+    ///
+    /// With further algorithms it is the first check all of their digests pass,
+    /// each being hashed only once the ones before passed.
+    ///
+    /// Candidates are hashed one at a time, the hashers picking SHA extensions at runtime
+    /// where available. [`spawn_workers`] hashes SHA-256 in [`Sha256Lanes`] first instead.
+    #[inline]
+    pub(crate) fn test(&mut self, input: &[u8]) -> Option<usize> {
+        self.hash_input(input);
+        let hash = self.words();
+        let first = self
+            .checks
+            .iter()
+            .position(|check| check.passes(&hash, self.words))?;
+        if self.also.is_empty() {
+            return Some(first);
+        }
+
+        let mut hashed = 0;
+        'checks: for check in first..self.checks.len() {
+            if !self.checks[check].passes(&hash, self.words) {
+                continue;
+            }
+            for (i, other) in self.also.iter_mut().enumerate() {
+                if i == hashed {
+                    other.hash(input);
+                    hashed += 1;
+                }
+                if !other.passes(check) {
+                    continue 'checks;
+                }
+            }
+            return Some(check);
+        }
+        None
+    }
+
+    /// Hashes `input` like [`Self::test`] and returns the number of zero nibbles
+    /// the digest starts or ends with
+    pub(crate) fn zero_nibbles(&mut self, input: &[u8], direction: Direction) -> usize {
+        self.hash_input(input);
+        zero_nibbles(&self.hash[..self.digest_size], direction)
+    }
+
+    /// Digests of the further algorithms for the last candidate that passed
+    pub(crate) fn also_digests(&self) -> Vec<DigestBytes> {
+        self.also.iter().map(|other| other.digest()).collect()
+    }
+
+    #[inline]
+    fn hash_input(&mut self, input: &[u8]) {
+        Digest::update(&mut self.hasher, input);
+        Digest::finalize_into_reset(
+            &mut self.hasher,
+            Output::<D>::from_mut_slice(&mut self.hash[..self.digest_size]),
+        );
+        if let Some(outer) = &self.outer {
+            // the hasher is free until the prefix is restored below
+            self.hasher.clone_from(outer);
+            let first = self.hash;
+            Digest::update(&mut self.hasher, &first[..self.digest_size]);
+            Digest::finalize_into_reset(
+                &mut self.hasher,
+                Output::<D>::from_mut_slice(&mut self.hash[..self.digest_size]),
+            );
+        }
+        if self.has_prefix {
+            self.hasher.clone_from(&self.seeded);
+        }
+    }
+
+    /// The last digest as words to check
+    #[inline]
+    fn words(&self) -> Words {
+        // the safe conversion compiles to the same copy as the transmute it replaced,
+        // which profiling showed to spend about 20% less CPU time outside `Sha256::finalize`
+        // than checking the digest byte by byte
+        to_words(self.hash)
+    }
+
+    /// The last digest
+    pub(crate) fn digest(&self) -> DigestBytes {
+        DigestBytes {
+            bytes: self.hash,
+            len: self.digest_size as u8,
+        }
+    }
+
+    /// The bytes hashed for `input`, prefix bytes included
+    pub(crate) fn message(&self, input: &[u8]) -> HexBytes {
+        HexBytes([&self.prefix_bytes[..], input].concat())
+    }
+
+    /// Result for candidate `n` after it passed check `check`, hashed from `input`
+    /// as the `attempts`th candidate
+    pub(crate) fn found(&self, n: usize, attempts: usize, check: usize, input: &[u8]) -> Found {
+        Found {
+            n,
+            line: None,
+            attempts,
+            check,
+            hash: self.digest(),
+            input: self.message(input),
+            also: self.also_digests(),
+        }
+    }
+}
+
+impl<D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send> Rehash for Tester<D> {
+    fn hash(&mut self, input: &[u8]) {
+        self.hash_input(input)
+    }
+
+    fn passes(&self, check: usize) -> bool {
+        self.checks[check].passes(&self.words(), self.words)
+    }
+
+    fn digest(&self) -> DigestBytes {
+        Tester::digest(self)
+    }
+}
+
+/// Number of zero nibbles `digest` starts or ends with
+fn zero_nibbles(digest: &[u8], direction: Direction) -> usize {
+    let (zero_bytes, next) = match direction {
+        Direction::Leading => {
+            let zero_bytes = digest.iter().take_while(|&&b| b == 0).count();
+            (zero_bytes, digest.get(zero_bytes).map(|b| b >> 4))
+        }
+        Direction::Trailing => {
+            let zero_bytes = digest.iter().rev().take_while(|&&b| b == 0).count();
+            let next = digest.len().checked_sub(zero_bytes + 1);
+            (zero_bytes, next.map(|i| digest[i] & 0xf))
+        }
+    };
+    zero_bytes * 2 + usize::from(next == Some(0))
+}
+
+/// Inner and outer padded HMAC keys, one block of `D` each
+#[cfg(feature = "hmac")]
+fn hmac_pads<D: Digest + BlockSizeUser>(key: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut block = vec![0; D::block_size()];
+    if key.len() > block.len() {
+        // long keys are hashed down first
+        block[..<D as Digest>::output_size()].copy_from_slice(&D::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner = block.iter().map(|b| b ^ 0x36).collect();
+    let outer = block.iter().map(|b| b ^ 0x5c).collect();
+    (inner, outer)
+}
+
+/// Lanes hashing candidates the way [`Tester`] does, if `D` is SHA-256 and the CPU has them
+fn sha256_lanes<D: 'static>(
+    prefix_bytes: &[u8],
+    double: bool,
+    hmac_key: Option<&[u8]>,
+) -> Option<Sha256Lanes> {
+    if TypeId::of::<D>() != TypeId::of::<Sha256>() {
+        return None;
+    }
+    match hmac_key {
+        #[cfg(feature = "hmac")]
+        Some(key) => {
+            let (inner_pad, outer_pad) = hmac_pads::<Sha256>(key);
+            Sha256Lanes::new(&[&inner_pad, prefix_bytes].concat(), Some(&outer_pad))
+        }
+        #[cfg(not(feature = "hmac"))]
+        Some(_) => None,
+        None => Sha256Lanes::new(prefix_bytes, double.then_some(&[])),
+    }
+}
+
+/// Spawns worker threads hashing consecutive encoded integers with `D`
+/// and sending those that pass the check to the results channel
+///
+/// Workers take batches of the range in turns and exit once it is exhausted,
+/// disconnecting the results channel after the last one.
+/// With [`Config::seed`] they draw candidates at random instead, until stopped.
+/// `D` can be any hash with digests of up to [`MAX_DIGEST_SIZE`] bytes,
+/// including ones from other crates.
+/// Sequential SHA-256 candidates are hashed [`LANES`] at a time where the CPU has AVX2 or AVX-512.
+/// Fails if `config` does not [validate](Config::validate) or `D`'s digests are longer,
+/// or if a seed is combined with a step, descending candidates or `resume`.
+pub fn spawn_workers<D: Digest + FixedOutputReset + BlockSizeUser + Clone + Send + 'static>(
+    config: Config,
+) -> Result<Workers, SearchError> {
+    config.validate_for::<D>(&[])?;
+    if let Some(seed) = config.seed {
+        config.reject(&[Field::Sequence, Field::Resume])?;
+        return Ok(spawn_random_workers::<D>(config, seed));
+    }
+    let Config {
+        checks,
+        workers,
+        start,
+        end,
+        step,
+        descending,
+        encoding,
+        width,
+        prefix_bytes,
+        double,
+        hmac_key,
+        also,
+        channel_capacity,
+        overflow,
+        pin,
+        numa,
+        seed: _,
+        cancel,
+        batch_size,
+        resume,
+    } = config;
+
+    let stop = CancelToken::flag(cancel);
+    // workers split the positions of candidates in the sequence, `start` being at 0,
+    // so no candidate is computed that would overflow
+    let candidates = Candidates::new(start, end, step, descending);
+    info!(
+        "spawning {workers} workers over {} candidates from {start} in steps of {}{step}, \
+         in batches of {batch_size}",
+        candidates.len,
+        if descending { "-" } else { "" },
+    );
+    let first_batches: Vec<_> = match resume {
+        Some(resume) => resume.iter().map(|&n| candidates.position(n)).collect(),
+        None => (0..workers).map(|i| batch_size.saturating_mul(i)).collect(),
+    };
+    let progress = Arc::new(Progress::new(
+        first_batches
+            .iter()
+            .map(|&position| AtomicUsize::new(candidates.frontier(position)))
+            .collect(),
+        workers,
+    ));
+    let (result_tx, result_rx) = results_channel(channel_capacity, overflow, &stop, &progress);
+    let setup = Arc::new((checks, prefix_bytes, hmac_key, also));
+
+    let handles = (0..workers).map(|i| {
+        let th_result_tx = result_tx.clone();
+        let th_stop = stop.clone();
+        let th_progress = progress.clone();
+        let th_setup = setup.clone();
+        let core = core_for(pin, numa, i);
+        let first_batch = first_batches[i];
+
+        thread::spawn(move || {
+            pin_to(core);
+            let (checks, prefix_bytes, hmac_key, also) = &*th_setup;
+            let mut tester =
+                Tester::<D>::new(checks, prefix_bytes, double, hmac_key.as_deref(), also);
+            let mut lanes = sha256_lanes::<D>(prefix_bytes, double, hmac_key.as_deref());
+            debug!("worker {i} started");
+            let mut results_buf = Vec::new();
+            let mut input = [0; MAX_ENCODED_SIZE];
+            let mut lane_input = [0; MAX_ENCODED_SIZE];
+            let stopped_at =
+                candidates.scan(first_batch, batch_size, workers, &th_stop, |batch, next| {
+                    let hashed = batch.len();
+                    // encodes, hashes and checks a candidate on its own,
+                    // `cargo bench -- hashing` compares this with bare sha256 hashing
+                    let mut test = |position| {
+                        let n = candidates.at(position);
+                        let candidate = encoding.encode(n, width, &mut input);
+                        if let Some(check) = tester.test(candidate) {
+                            results_buf.push(tester.found(n, position + 1, check, candidate));
+                        }
+                    };
+                    let mut positions = batch;
+                    if let Some(lanes) = &mut lanes {
+                        // SHA-256 is hashed in SIMD lanes first, only the few candidates passing
+                        // are tested again on their own for their result,
+                        // `cargo bench -- lanes` compares this with testing all of them that way
+                        while positions.len() >= LANES {
+                            let group = positions.start..positions.start + LANES;
+                            let loaded = group.clone().enumerate().all(|(lane, position)| {
+                                let n = candidates.at(position);
+                                lanes.set(lane, encoding.encode(n, width, &mut lane_input))
+                            });
+                            if !loaded {
+                                // the rest of the batch crosses to candidates of another length
+                                break;
+                            }
+                            lanes.hash();
+                            for (lane, position) in group.enumerate() {
+                                if lanes.passes(lane, checks) {
+                                    test(position);
+                                }
+                            }
+                            positions.start += LANES;
+                        }
+                    }
+                    positions.for_each(test);
+
+                    if !th_result_tx.finish_batch(i, hashed, &mut results_buf) {
+                        return false;
+                    }
+                    // results of the batch must be in the channel before it is marked scanned
+                    th_progress.frontiers[i].store(candidates.frontier(next), Ordering::Release);
+                    true
+                });
+            if stopped_at >= candidates.len {
+                debug!("worker {i} finished its part of the range");
+                th_progress.finish();
+            } else {
+                debug!("worker {i} stopped at {}", candidates.at(stopped_at));
+            }
+        })
+    });
+    Ok(Workers {
+        handles: handles.collect(),
+        results: result_rx,
+        stop,
+        progress,
+    })
+}
+
+/// Hashes the single candidate `n` the way workers would with `config`,
+/// returning its digest and the index of the first check it passes
+///
+/// Only the checks, encoding, width, prefix bytes and second round of `config` apply.
+pub fn verify<D: Digest + FixedOutputReset + BlockSizeUser + Clone>(
+    config: &Config,
+    n: usize,
+) -> Result<(DigestBytes, Option<usize>), SearchError> {
+    config.validate_for::<D>(&[])?;
+    let mut tester = Tester::<D>::new(
+        &config.checks,
+        &config.prefix_bytes,
+        config.double,
+        config.hmac_key.as_deref(),
+        &config.also,
+    );
+    let mut input = [0; MAX_ENCODED_SIZE];
+    let check = tester.test(config.encoding.encode(n, config.width, &mut input));
+    Ok((tester.digest(), check))
+}
+
+/// Sending end of the results channel, along with the receiving end
+/// if the oldest results are dropped to make room
+#[derive(Clone)]
+pub(crate) struct ResultSender {
+    tx: Sender<Found>,
+    oldest: Option<Receiver<Found>>,
+    /// a stopped search is not drained, so sending gives up once it is set
+    stop: Arc<AtomicBool>,
+    progress: Arc<Progress>,
+}
+
+/// Channel for results holding `capacity` of them, or any number if [`UNBOUNDED_CHANNEL`],
+/// for a search stopped by `stop` counting its batches in `progress`
+pub(crate) fn results_channel(
+    capacity: usize,
+    overflow: Overflow,
+    stop: &Arc<AtomicBool>,
+    progress: &Arc<Progress>,
+) -> (ResultSender, Receiver<Found>) {
+    let (tx, rx) = if capacity == UNBOUNDED_CHANNEL {
+        unbounded()
+    } else {
+        bounded(capacity)
+    };
+    let oldest = matches!(overflow, Overflow::DropOldest).then(|| rx.clone());
+    let sender = ResultSender {
+        tx,
+        oldest,
+        stop: stop.clone(),
+        progress: progress.clone(),
+    };
+    (sender, rx)
+}
+
+impl ResultSender {
+    /// Counts a batch of `hashes` candidates hashed by worker `worker` and sends its results,
+    /// waiting while the channel is full or dropping the oldest ones,
+    /// fails if the search was stopped or nobody receives results anymore
+    pub(crate) fn finish_batch(&self, worker: usize, hashes: usize, buf: &mut Vec<Found>) -> bool {
+        self.progress.record_batch(worker, hashes, buf.len());
+        if let Some(oldest) = &self.oldest {
+            for mut found in buf.drain(..) {
+                // the receiving end held here keeps the channel connected
+                while let Err(TrySendError::Full(unsent)) = self.tx.try_send(found) {
+                    if oldest.try_recv().is_ok() {
+                        self.progress.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    found = unsent;
+                }
+            }
+            return true;
+        }
+        for mut found in buf.drain(..) {
+            loop {
+                match self.tx.send_timeout(found, STOP_POLL_INTERVAL) {
+                    Ok(()) => break,
+                    Err(SendTimeoutError::Timeout(unsent))
+                        if !self.stop.load(Ordering::Relaxed) =>
+                    {
+                        found = unsent
+                    }
+                    Err(_) => return false,
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Core worker `i` should be pinned to, cycling through the available ones,
+/// those of one NUMA node before the next with `numa`
+pub(crate) fn core_for(pin: bool, numa: bool, i: usize) -> Option<CoreId> {
+    if !pin {
+        return None;
+    }
+    let mut cores = core_affinity::get_core_ids()?;
+    if let Some(nodes) = numa.then(numa_nodes).flatten() {
+        // cores of no known node go last
+        cores.sort_by_key(|core| {
+            nodes
+                .iter()
+                .position(|node| node.contains(&core.id))
+                .unwrap_or(usize::MAX)
+        });
+    }
+    cores.get(i % cores.len().max(1)).copied()
+}
+
+/// Cores of every NUMA node in node order, as Linux lists them in sysfs
+fn numa_nodes() -> Option<Vec<Vec<usize>>> {
+    let mut nodes: Vec<(usize, Vec<usize>)> = fs::read_dir("/sys/devices/system/node")
+        .ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let node = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse()
+                .ok()?;
+            let cores = fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            Some((node, parse_core_list(cores.trim())?))
+        })
+        .collect();
+    nodes.sort_unstable_by_key(|(node, _)| *node);
+    Some(nodes.into_iter().map(|(_, cores)| cores).collect())
+}
+
+/// Parses a kernel core list like `0-3,8-11`
+fn parse_core_list(list: &str) -> Option<Vec<usize>> {
+    let mut cores = Vec::new();
+    for part in list.split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => cores.extend(first.parse::<usize>().ok()?..=last.parse().ok()?),
+            None => cores.push(part.parse().ok()?),
+        }
+    }
+    Some(cores)
+}
+
+/// Pins the calling thread to `core`, leaving it unpinned if that is not supported
+///
+/// Workers call it before building their [`Tester`], which then ends up on the NUMA node
+/// of the core since the pinned thread touches it first.
+pub(crate) fn pin_to(core: Option<CoreId>) {
+    if let Some(core) = core {
+        core_affinity::set_for_current(core);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Sha256;
+
+    fn config(encoding: Encoding, width: usize) -> Config {
+        Config {
+            encoding,
+            width,
+            ..Config::new(Check::EMPTY, 1)
+        }
+    }
+
+    fn sha256(config: &Config, n: usize) -> String {
+        verify::<Sha256>(config, n).unwrap().0.to_string()
+    }
+
+    #[test]
+    fn encoded_digests() {
+        // sha256 of 12345 serialized each way, computed independently
+        let cases = [
+            (
+                Encoding::Le,
+                8,
+                "e1543551249113046932741cc28f36b4bbcc542233eb5094874072d3167f160a",
+            ),
+            (
+                Encoding::Be,
+                8,
+                "f72a57b9baa23c55ca546c5677986c8b75c4509a09489f5d2634fb586740100a",
+            ),
+            (
+                Encoding::Decimal,
+                8,
+                "5994471abb01112afcc18159f6cc74b4f511b99806da59b3caf5a9c173cacfc5",
+            ),
+            (
+                Encoding::Hex,
+                8,
+                "508d030c81626ebe8bd8961bd75059dbe7c22ed798825c27885ec524ea0bb45a",
+            ),
+        ];
+        for (encoding, width, digest) in cases {
+            assert_eq!(
+                sha256(&config(encoding, width), 12345),
+                digest,
+                "{encoding}"
+            );
+        }
+    }
+
+    #[test]
+    fn prefixed_digest() {
+        let config = Config {
+            prefix_bytes: vec![0xde, 0xad, 0xbe, 0xef],
+            ..config(Encoding::Le, 8)
+        };
+        // sha256(deadbeef ++ 12345 as 8 little-endian bytes)
+        assert_eq!(
+            sha256(&config, 12345),
+            "72892d20475bb76911310e0a46afacc083cb34ed6c54d189b75c9eaa46445143"
+        );
+    }
+
+    #[test]
+    fn lanes_find_what_single_digests_find() {
+        let search = Config {
+            end: Some(3000),
+            batch_size: 50,
+            encoding: Encoding::Decimal,
+            ..Config::new(Check::zeros(1, 32, Direction::Trailing), 2)
+        };
+        let mut searches = vec![
+            // decimal candidates change length within groups of lanes
+            search.clone(),
+            Config {
+                prefix_bytes: vec![7; 70],
+                ..search.clone()
+            },
+            Config {
+                double: true,
+                ..search.clone()
+            },
+        ];
+        if cfg!(feature = "hmac") {
+            searches.push(Config {
+                hmac_key: Some(b"key".to_vec()),
+                ..search.clone()
+            });
+        }
+        for config in searches {
+            let expected: Vec<_> = (1..3000)
+                .filter_map(|n| {
+                    let (digest, check) = verify::<Sha256>(&config, n).unwrap();
+                    check.map(|_| (n, digest.to_string()))
+                })
+                .collect();
+            let workers = spawn_workers::<Sha256>(config).unwrap();
+            let mut found: Vec<_> = workers
+                .results()
+                .iter()
+                .map(|found| (found.n, found.hash.to_string()))
+                .collect();
+            found.sort_unstable();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn tiny_range_without_results_ends() {
+        let config = Config {
+            end: Some(100),
+            batch_size: 7,
+            ..Config::new(Check::NEVER, 3)
+        };
+        let workers = spawn_workers::<Sha256>(config).unwrap();
+        // every worker finishing disconnects the channel
+        assert!(workers.results().recv().is_err());
+        assert!(workers.progress().exhausted());
+        assert_eq!(workers.progress().hashes(), 99);
+    }
+
+    #[test]
+    fn batches_stop_at_the_end_of_the_counter() {
+        let config = Config {
+            start: usize::MAX - 10,
+            batch_size: 4,
+            ..Config::new(Check::EMPTY, 3)
+        };
+        let workers = spawn_workers::<Sha256>(config).unwrap();
+        let mut found: Vec<_> = workers.results().iter().map(|found| found.n).collect();
+        found.sort_unstable();
+        // an unbounded search stops short of `usize::MAX` instead of wrapping around
+        assert_eq!(found, (usize::MAX - 10..usize::MAX).collect::<Vec<_>>());
+        assert!(workers.progress().exhausted());
+    }
+
+    #[test]
+    fn best_and_histogram_follow_the_sequence() {
+        // 2000, 1993, ..., 5
+        let config = Config {
+            start: 2000,
+            step: 7,
+            descending: true,
+            batch_size: 16,
+            ..Config::new(Check::EMPTY, 3)
+        };
+        let histogram = crate::zero_histogram::<Sha256>(&config, Direction::Trailing).unwrap();
+        assert_eq!(histogram.iter().sum::<u64>(), 286);
+
+        let workers = crate::spawn_best_workers::<Sha256>(config, Direction::Trailing).unwrap();
+        let records: Vec<_> = workers.results().iter().map(|found| found.n).collect();
+        assert!(records.iter().all(|n| n % 7 == 5), "{records:?}");
+        assert_eq!(workers.progress().hashes(), 286);
+    }
+
+    #[test]
+    fn random_search_of_a_bounded_range_ends() {
+        let config = Config {
+            end: Some(100),
+            seed: Some(1),
+            batch_size: 16,
+            ..Config::new(Check::EMPTY, 3)
+        };
+        let workers = spawn_workers::<Sha256>(config).unwrap();
+        let drawn: std::collections::BTreeSet<_> =
+            workers.results().iter().map(|found| found.n).collect();
+        assert_eq!(drawn, (1..100).collect());
+        assert!(workers.progress().exhausted());
+    }
+
+    #[test]
+    fn unsupported_fields_are_rejected() {
+        let random = Config {
+            seed: Some(1),
+            step: 2,
+            ..Config::new(Check::EMPTY, 1)
+        };
+        assert!(matches!(
+            spawn_workers::<Sha256>(random),
+            Err(SearchError::InvalidConfig(
+                "this search does not support a step or descending candidates"
+            ))
+        ));
+        let resumed = Config {
+            end: Some(10),
+            resume: Some(vec![5]),
+            ..Config::new(Check::EMPTY, 1)
+        };
+        assert!(matches!(
+            crate::zero_histogram::<Sha256>(&resumed, Direction::Trailing),
+            Err(SearchError::InvalidConfig("this search cannot resume"))
+        ));
+    }
+
+    #[test]
+    fn full_channel_blocks_without_losing_results() {
+        let search = |channel_capacity| Config {
+            end: Some(2000),
+            batch_size: 16,
+            channel_capacity,
+            ..Config::new(Check::zeros(1, 32, Direction::Trailing), 4)
+        };
+        let workers = spawn_workers::<Sha256>(search(UNBOUNDED_CHANNEL)).unwrap();
+        let mut expected: Vec<_> = workers.results().iter().map(|found| found.n).collect();
+        expected.sort_unstable();
+
+        let workers = spawn_workers::<Sha256>(search(1)).unwrap();
+        let mut found = Vec::new();
+        for result in workers.results() {
+            // the workers fill the channel and wait while this falls behind
+            thread::sleep(Duration::from_micros(100));
+            found.push(result.n);
+        }
+        found.sort_unstable();
+        assert_eq!(found, expected);
+
+        // workers waiting on the full channel exit once stopped
+        let workers = spawn_workers::<Sha256>(search(1)).unwrap();
+        workers.results().recv().unwrap();
+        thread::sleep(Duration::from_millis(10));
+        assert!(workers.stop().len() <= 1);
+    }
+
+    fn hex_digest<D: Digest + FixedOutputReset + BlockSizeUser + Clone>(
+        mut tester: Tester<D>,
+        input: &[u8],
+    ) -> String {
+        tester.hash_input(input);
+        tester.digest().to_string()
+    }
+
+    #[test]
+    fn double_sha256() {
+        let tester = Tester::<Sha256>::new(&[], &[], true, None, &[]);
+        assert_eq!(
+            hex_digest(tester, b"hello"),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+        // checks apply to the second digest, 2501b9.. for 12345 as 8 little-endian bytes
+        let prefix = Check::pattern(&"2501b9".parse().unwrap(), 32, Direction::Leading);
+        let config = Config {
+            double: true,
+            ..Config::new(prefix, 1)
+        };
+        assert_eq!(verify::<Sha256>(&config, 12345).unwrap().1, Some(0));
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn hmac_rfc_4231() {
+        use sha2::Sha512;
+
+        // test case 1
+        let key = [0x0b; 20];
+        assert_eq!(
+            hex_digest(
+                Tester::<Sha256>::new(&[], &[], false, Some(&key), &[]),
+                b"Hi There"
+            ),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hex_digest(
+                Tester::<Sha512>::new(&[], &[], false, Some(&key), &[]),
+                b"Hi There"
+            ),
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+             daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"
+        );
+        // test case 2, with the message split between prefix bytes and candidate
+        let prefix = b"what do ya want for nothing";
+        assert_eq!(
+            hex_digest(
+                Tester::<Sha256>::new(&[], prefix, false, Some(b"Jefe"), &[]),
+                b"?"
+            ),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex_digest(
+                Tester::<Sha512>::new(&[], prefix, false, Some(b"Jefe"), &[]),
+                b"?"
+            ),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+             9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+        // test case 6, with a key longer than a block of either, which is hashed down first
+        let key = [0xaa; 131];
+        let message = b"Test Using Larger Than Block-Size Key - Hash Key First";
+        assert_eq!(
+            hex_digest(
+                Tester::<Sha256>::new(&[], &[], false, Some(&key), &[]),
+                message
+            ),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        assert_eq!(
+            hex_digest(
+                Tester::<Sha512>::new(&[], &[], false, Some(&key), &[]),
+                message
+            ),
+            "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+             6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"
+        );
+    }
+
+    #[test]
+    fn core_lists() {
+        assert_eq!(
+            parse_core_list("0-3,8,10-11"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_core_list(""), Some(vec![]));
+        assert_eq!(parse_core_list("0-x"), None);
+    }
+}